@@ -0,0 +1,102 @@
+use std::future::Future;
+
+use serde::Serialize;
+use tokio::sync::watch::{Receiver, Sender};
+
+use crate::{clicker_controller::MeasureResult, clicker_interface};
+
+/// Состояние автоматического запуска измерений
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "state")]
+pub enum AutoMeasureState {
+    /// Автоматический запуск выключен
+    Disabled,
+    /// Ожидание установки резонатора
+    WaitingInsertion,
+    /// Резонатор установлен, идет измерение
+    Measuring,
+    /// Ожидание снятия резонатора
+    WaitingRemoval,
+}
+
+/// Определение наличия резонатора по отсчетам устройства.
+/// Резонатора нет, если отсчет NaN, 0 или вне пределов `freq_min..=freq_max` / `0..=rk_max`.
+/// Смена наличия засчитывается после `samples` отсчетов подряд
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PresenceDetector {
+    pub freq_min: f32,
+    pub freq_max: f32,
+    pub rk_max: f32,
+    pub samples: u32,
+}
+
+impl PresenceDetector {
+    pub fn new(freq_min: f32, freq_max: f32, rk_max: f32, samples: u32) -> Self {
+        Self {
+            freq_min,
+            freq_max,
+            rk_max,
+            samples,
+        }
+    }
+
+    /// Отсчет `sample` получен с установленным резонатором
+    pub fn is_present(&self, sample: &clicker_interface::MeasureResult) -> bool {
+        match *sample {
+            clicker_interface::MeasureResult::Freq(f) => {
+                f != 0.0 && (self.freq_min..=self.freq_max).contains(&f)
+            }
+            clicker_interface::MeasureResult::Rk(rk) => rk > 0.0 && rk <= self.rk_max,
+        }
+    }
+
+    /// Дождаться `samples` отсчетов подряд с наличием резонатора `present`.
+    /// `false` - канал отсчетов закрыт
+    async fn wait(&self, status_rx: &mut Receiver<MeasureResult>, present: bool) -> bool {
+        status_rx.mark_unchanged();
+
+        let mut count = 0;
+        while count < self.samples.max(1) {
+            if status_rx.changed().await.is_err() {
+                return false;
+            }
+            if self.is_present(&status_rx.borrow().result) == present {
+                count += 1;
+            } else {
+                count = 0;
+            }
+        }
+        true
+    }
+}
+
+/// Ждать снятия резонатора, затем установки нового и запускать `measure`, пока открыт канал отсчетов
+pub(crate) async fn auto_measure_task<F, Fut>(
+    mut status_rx: Receiver<MeasureResult>,
+    state_tx: &Sender<AutoMeasureState>,
+    detector: PresenceDetector,
+    mut measure: F,
+) where
+    F: FnMut() -> Fut + Send,
+    Fut: Future<Output = ()> + Send,
+{
+    // Резонатор, установленный до включения режима, не измеряется
+    loop {
+        state_tx.send_replace(AutoMeasureState::WaitingRemoval);
+        if !detector.wait(&mut status_rx, false).await {
+            break;
+        }
+
+        state_tx.send_replace(AutoMeasureState::WaitingInsertion);
+        if !detector.wait(&mut status_rx, true).await {
+            break;
+        }
+
+        tracing::info!("Resonator inserted, starting measure");
+        state_tx.send_replace(AutoMeasureState::Measuring);
+        measure().await;
+    }
+
+    tracing::error!("Auto measure stopped: status channel closed");
+    state_tx.send_replace(AutoMeasureState::Disabled);
+}
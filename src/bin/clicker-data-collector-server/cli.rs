@@ -0,0 +1,87 @@
+use std::{net::IpAddr, path::PathBuf, str::FromStr};
+
+use clap::Parser;
+
+use crate::handlers::device::FAKE_PORT;
+
+/// Источник данных измерений
+#[derive(Clone, Debug)]
+pub(crate) enum Device {
+    /// Реальное устройство: последовательный порт или tcp://host:port
+    Port(String),
+    /// Эмуляция устройства по профилю Emulation
+    Fake,
+    /// Воспроизведение файла захвата
+    Replay(PathBuf),
+}
+
+impl FromStr for Device {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            Err("пустое имя устройства".to_string())
+        } else if s == FAKE_PORT {
+            Ok(Self::Fake)
+        } else if let Some(path) = s.strip_prefix("replay:") {
+            Ok(Self::Replay(PathBuf::from(path)))
+        } else {
+            Ok(Self::Port(s.to_string()))
+        }
+    }
+}
+
+/// Сборщик данных с устройства "Щелкунчик"
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub(crate) struct Cli {
+    /// Устройство: COMx, /dev/ttyX, tcp://host:port, fake или replay:<файл захвата>.
+    /// По умолчанию - RkMeterPort из файла настроек
+    #[arg(short, long)]
+    pub device: Option<Device>,
+
+    /// Ускорение воспроизведения файла захвата, 0 - без задержек
+    #[arg(long, default_value_t = 1.0)]
+    pub replay_speed: f64,
+
+    /// Номинальная частота эмуляции (--device fake), Гц, заменяет значение из профиля
+    #[arg(long, env = "EMULATE_FREQ")]
+    pub emulate_freq: Option<f32>,
+
+    /// Профиль эмуляции (JSON, формат раздела Emulation файла настроек),
+    /// заменяет Emulation из файла настроек
+    #[arg(long)]
+    pub emulation: Option<PathBuf>,
+
+    /// Записывать обмен с устройством в файл
+    #[arg(long, env = "CLICKER_CAPTURE")]
+    pub capture: Option<PathBuf>,
+
+    /// Альтернативный файл настроек
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Файл каталога типов резонаторов, по умолчанию - resonator_types.json рядом с файлом настроек
+    #[arg(long)]
+    pub catalog: Option<PathBuf>,
+
+    /// Порт web-интерфейса, заменяет WebPort из файла настроек
+    #[arg(short = 'p', long)]
+    pub web_port: Option<u16>,
+
+    /// Адрес, на котором принимаются подключения к web-интерфейсу
+    #[arg(short, long, default_value = "0.0.0.0")]
+    pub bind: IpAddr,
+
+    /// Интервал опроса устройства, мс
+    #[arg(short, long, default_value_t = 250)]
+    pub interval: u64,
+
+    /// Циклов переключения Rk -> F -> Rk на измерение, заменяет Cycles из файла настроек
+    #[arg(long)]
+    pub cycles: Option<u32>,
+
+    /// Фильтр логирования в формате RUST_LOG
+    #[arg(short, long)]
+    pub log: Option<String>,
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+
+use clicker_data_collector::{
+    data_model::DataModel, ClickerController, Config, MeasureFeed, ResonatorCatalog,
+};
+use tokio::sync::Mutex;
+
+use crate::handlers::handle_control::{append_resonator, start_measure_task};
+
+/// Включить автоматический запуск измерений, результаты добавляются в конец таблицы
+pub(crate) async fn start_auto(
+    config: Arc<Mutex<Config>>,
+    data_model: Arc<Mutex<DataModel>>,
+    catalog: Arc<Mutex<ResonatorCatalog>>,
+    clicker_ctrl: Arc<Mutex<ClickerController>>,
+    feed: MeasureFeed,
+) {
+    let detector = config.lock().await.presence_detector();
+    tracing::info!("Auto measure enabled: {:?}", detector);
+
+    let ctrl = clicker_ctrl.clone();
+    let measure = move || {
+        let (config, data_model, catalog, ctrl, feed) = (
+            config.clone(),
+            data_model.clone(),
+            catalog.clone(),
+            ctrl.clone(),
+            feed.clone(),
+        );
+        async move {
+            match start_measure_task(config, data_model, catalog, ctrl, feed, append_resonator)
+                .await
+            {
+                Ok(task) => {
+                    let _ = task.await;
+                }
+                Err(e) => tracing::error!("Failed to start auto measure: {e}"),
+            }
+        }
+    };
+
+    clicker_ctrl
+        .lock()
+        .await
+        .start_auto(detector, measure)
+        .await;
+}
+
+// Состояние автоматического запуска измерений
+pub(crate) async fn handle_auto_get(
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+) -> impl IntoResponse {
+    Json(clicker_ctrl.lock().await.auto_state())
+}
+
+// Включить (true) или выключить (false) автоматический запуск измерений
+pub(crate) async fn handle_auto_put(
+    State(config): State<Arc<Mutex<Config>>>,
+    State(data_model): State<Arc<Mutex<DataModel>>>,
+    State(catalog): State<Arc<Mutex<ResonatorCatalog>>>,
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+    State(feed): State<MeasureFeed>,
+    body: String,
+) -> impl IntoResponse {
+    tracing::debug!("handle_auto_put: {}", body);
+
+    if body.to_uppercase() == "TRUE" {
+        start_auto(config, data_model, catalog, clicker_ctrl.clone(), feed).await;
+    } else {
+        clicker_ctrl.lock().await.stop_auto().await;
+    }
+
+    let state = clicker_ctrl.lock().await.auto_state();
+    (StatusCode::OK, Json(state))
+}
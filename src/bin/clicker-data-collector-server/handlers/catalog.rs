@@ -0,0 +1,80 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+
+use clicker_data_collector::{data_model::DataModel, ResonatorCatalog, ResonatorType};
+use tokio::sync::Mutex;
+
+/// Файл каталога типов резонаторов
+#[derive(Clone)]
+pub(crate) struct CatalogFile(pub PathBuf);
+
+// Получить список типов резонаторов
+pub(crate) async fn handle_catalog_get(
+    State(catalog): State<Arc<Mutex<ResonatorCatalog>>>,
+) -> impl IntoResponse {
+    Json(catalog.lock().await.types().to_vec())
+}
+
+// Добавить или заменить тип резонатора
+pub(crate) async fn handle_catalog_put(
+    State(catalog): State<Arc<Mutex<ResonatorCatalog>>>,
+    State(catalog_file): State<CatalogFile>,
+    State(data_model): State<Arc<Mutex<DataModel>>>,
+    Json(resonator_type): Json<ResonatorType>,
+) -> impl IntoResponse {
+    tracing::debug!("handle_catalog_put: {:?}", resonator_type);
+
+    if let Err(e) = resonator_type.validate() {
+        return (StatusCode::RANGE_NOT_SATISFIABLE, e).into_response();
+    }
+
+    let mut data_model = data_model.lock().await;
+    let mut catalog = catalog.lock().await;
+    catalog.insert(resonator_type);
+    update(&catalog, &catalog_file, &mut data_model)
+}
+
+// Удалить тип резонатора
+pub(crate) async fn handle_catalog_delete(
+    State(catalog): State<Arc<Mutex<ResonatorCatalog>>>,
+    State(catalog_file): State<CatalogFile>,
+    State(data_model): State<Arc<Mutex<DataModel>>>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    tracing::debug!("handle_catalog_delete: {}", name);
+
+    let mut data_model = data_model.lock().await;
+    let mut catalog = catalog.lock().await;
+    if catalog.remove(&name).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    update(&catalog, &catalog_file, &mut data_model)
+}
+
+/// Сохранить каталог и пересчитать годность измеренных резонаторов.
+/// Как и в остальных обработчиках, `data_model` блокируется раньше каталога
+fn update(
+    catalog: &ResonatorCatalog,
+    catalog_file: &CatalogFile,
+    data_model: &mut DataModel,
+) -> axum::response::Response {
+    data_model.evaluate(catalog);
+
+    match catalog.save(&catalog_file.0) {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(e) => {
+            tracing::error!(
+                "Failed to save resonator catalog {:?}: {}",
+                catalog_file.0,
+                e
+            );
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
@@ -1,10 +1,13 @@
+use std::sync::Arc;
+
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_template::{Key, RenderHtml};
 
-use clicker_data_collector::Config;
+use clicker_data_collector::{Config, MeasureStrategyKind, OutlierMethod, QuantileMethod};
 use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 
-use crate::AppEngine;
+use crate::{handlers::catalog::CatalogFile, AppEngine};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UpdateAndSaveConfigValues {
@@ -16,52 +19,155 @@ pub struct UpdateAndSaveConfigValues {
 
     #[serde(rename = "Cycles", skip_serializing_if = "Option::is_none")]
     cycles: Option<u32>,
+
+    #[serde(rename = "Retries", skip_serializing_if = "Option::is_none")]
+    retries: Option<u32>,
+
+    #[serde(rename = "MeasureStrategy", skip_serializing_if = "Option::is_none")]
+    measure_strategy: Option<MeasureStrategyKind>,
+
+    #[serde(rename = "SamplesPerMode", skip_serializing_if = "Option::is_none")]
+    samples_per_mode: Option<u32>,
+
+    #[serde(rename = "MeasureDurationMs", skip_serializing_if = "Option::is_none")]
+    measure_duration_ms: Option<u64>,
+
+    #[serde(rename = "FreqIqrThreshold", skip_serializing_if = "Option::is_none")]
+    freq_iqr_threshold: Option<f32>,
+
+    #[serde(rename = "RkIqrThreshold", skip_serializing_if = "Option::is_none")]
+    rk_iqr_threshold: Option<f32>,
+
+    #[serde(rename = "MaxMeasureTimeMs", skip_serializing_if = "Option::is_none")]
+    max_measure_time_ms: Option<u64>,
+
+    #[serde(rename = "SettlingSamples", skip_serializing_if = "Option::is_none")]
+    settling_samples: Option<u32>,
+
+    #[serde(rename = "SettlingTimeMs", skip_serializing_if = "Option::is_none")]
+    settling_time_ms: Option<u64>,
+
+    #[serde(
+        rename = "FirstSwitchTimeoutMs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    first_switch_timeout_ms: Option<u64>,
+
+    #[serde(rename = "PhaseTimeoutMs", skip_serializing_if = "Option::is_none")]
+    phase_timeout_ms: Option<u64>,
+
+    #[serde(rename = "MeasureTimeoutMs", skip_serializing_if = "Option::is_none")]
+    measure_timeout_ms: Option<u64>,
+
+    #[serde(rename = "QuantileMethod", skip_serializing_if = "Option::is_none")]
+    quantile_method: Option<QuantileMethod>,
+
+    #[serde(rename = "FenceMultiplier", skip_serializing_if = "Option::is_none")]
+    fence_multiplier: Option<f64>,
+
+    #[serde(rename = "ConfidenceLevel", skip_serializing_if = "Option::is_none")]
+    confidence_level: Option<f64>,
+
+    #[serde(rename = "OutlierRejection", skip_serializing_if = "Option::is_none")]
+    outlier_rejection: Option<OutlierMethod>,
+
+    #[serde(rename = "OutlierMultiplier", skip_serializing_if = "Option::is_none")]
+    outlier_multiplier: Option<f64>,
+
+    #[serde(rename = "MeasureRetries", skip_serializing_if = "Option::is_none")]
+    measure_retries: Option<u32>,
+
+    #[serde(rename = "RetryFreqIqr", skip_serializing_if = "Option::is_none")]
+    retry_freq_iqr: Option<f32>,
+
+    #[serde(rename = "RetryRkIqr", skip_serializing_if = "Option::is_none")]
+    retry_rk_iqr: Option<f32>,
+
+    #[serde(rename = "AutoFreqMin", skip_serializing_if = "Option::is_none")]
+    auto_freq_min: Option<f32>,
+
+    #[serde(rename = "AutoFreqMax", skip_serializing_if = "Option::is_none")]
+    auto_freq_max: Option<f32>,
+
+    #[serde(rename = "AutoRkMax", skip_serializing_if = "Option::is_none")]
+    auto_rk_max: Option<f32>,
+
+    #[serde(rename = "AutoConfirmSamples", skip_serializing_if = "Option::is_none")]
+    auto_confirm_samples: Option<u32>,
 }
 
 pub(crate) async fn handle_config(
     State(engine): State<AppEngine>,
-    State(config): State<Config>,
+    State(config): State<Arc<Mutex<Config>>>,
     State(config_file): State<std::path::PathBuf>,
+    State(catalog_file): State<CatalogFile>,
 ) -> impl IntoResponse {
     #[derive(Serialize)]
     struct ConfigModel {
         pub config_file: String,
+        pub catalog_file: String,
         pub config: Config,
     }
 
     let model: ConfigModel = ConfigModel {
         config_file: config_file.to_string_lossy().to_string(),
-        config,
+        catalog_file: catalog_file.0.to_string_lossy().to_string(),
+        config: config.lock().await.clone(),
     };
 
     RenderHtml(Key("config".to_owned()), engine, model)
 }
 
 pub(crate) async fn handle_config_and_save(
-    State(mut config): State<Config>,
+    State(current_config): State<Arc<Mutex<Config>>>,
+    State(config_file): State<std::path::PathBuf>,
     Json(input): Json<UpdateAndSaveConfigValues>,
 ) -> impl IntoResponse {
     tracing::debug!("handle_update_config_and_save: {:?}", input);
 
+    let mut current_config = current_config.lock().await;
+    let mut config = current_config.clone();
+
+    match apply_config_values(&input, &mut config) {
+        Ok(true) => {
+            // В файл сохраняются только измененные значения, без переопределений из командной строки
+            let (mut file_config, _) = Config::load_from(config_file.clone());
+            match apply_config_values(&input, &mut file_config) {
+                Ok(_) => file_config.save_to(&config_file),
+                Err(e) => tracing::error!("Settings not saved to {:?}: {}", config_file, e),
+            }
+
+            *current_config = config;
+        }
+        Ok(false) => {}
+        Err(e) => return (StatusCode::RANGE_NOT_SATISFIABLE, e),
+    }
+
+    (StatusCode::OK, "Done")
+}
+
+fn apply_config_values(
+    input: &UpdateAndSaveConfigValues,
+    config: &mut Config,
+) -> Result<bool, &'static str> {
     let mut modified = false;
 
-    if let Some(rk_meter_port) = input.rk_meter_port {
+    if let Some(rk_meter_port) = &input.rk_meter_port {
         if rk_meter_port.is_empty() {
-            return (
-                StatusCode::RANGE_NOT_SATISFIABLE,
-                "TargetFreq Должен быть больше 0",
-            );
+            return Err("TargetFreq Должен быть больше 0");
+        }
+        if let Some(addr) = rk_meter_port.strip_prefix(clicker_data_collector::TCP_PREFIX) {
+            if !addr.contains(':') {
+                return Err("RkMeterPort Сетевой адрес должен иметь вид tcp://host:port");
+            }
         }
-        config.rk_meter_port = rk_meter_port;
+        config.rk_meter_port = rk_meter_port.clone();
         modified = true;
     }
 
     if let Some(web_port) = input.web_port {
         if web_port < 1024 {
-            return (
-                StatusCode::RANGE_NOT_SATISFIABLE,
-                "WebPort Должен быть больше 1024",
-            );
+            return Err("WebPort Должен быть больше 1024");
         }
         config.web_port = web_port;
         modified = true;
@@ -69,18 +175,188 @@ pub(crate) async fn handle_config_and_save(
 
     if let Some(cycles) = input.cycles {
         if cycles < 1 {
-            return (
-                StatusCode::RANGE_NOT_SATISFIABLE,
-                "Cycles Должен быть больше 0",
-            );
+            return Err("Cycles Должен быть больше 0");
         }
         config.cycles = cycles;
         modified = true;
     }
 
-    if modified {
-        config.save();
+    if let Some(retries) = input.retries {
+        config.retries = retries;
+        modified = true;
     }
 
-    (StatusCode::OK, "Done")
+    if let Some(measure_strategy) = input.measure_strategy {
+        config.measure_strategy = measure_strategy;
+        modified = true;
+    }
+
+    if let Some(samples_per_mode) = input.samples_per_mode {
+        if samples_per_mode < 1 {
+            return Err("SamplesPerMode Должен быть больше 0");
+        }
+        config.samples_per_mode = samples_per_mode;
+        modified = true;
+    }
+
+    if let Some(measure_duration_ms) = input.measure_duration_ms {
+        if measure_duration_ms < 1 {
+            return Err("MeasureDurationMs Должен быть больше 0");
+        }
+        config.measure_duration_ms = measure_duration_ms;
+        modified = true;
+    }
+
+    if let Some(freq_iqr_threshold) = input.freq_iqr_threshold {
+        if freq_iqr_threshold.is_nan() || freq_iqr_threshold <= 0.0 {
+            return Err("FreqIqrThreshold Должен быть больше 0");
+        }
+        config.freq_iqr_threshold = freq_iqr_threshold;
+        modified = true;
+    }
+
+    if let Some(rk_iqr_threshold) = input.rk_iqr_threshold {
+        if rk_iqr_threshold.is_nan() || rk_iqr_threshold <= 0.0 {
+            return Err("RkIqrThreshold Должен быть больше 0");
+        }
+        config.rk_iqr_threshold = rk_iqr_threshold;
+        modified = true;
+    }
+
+    if let Some(max_measure_time_ms) = input.max_measure_time_ms {
+        if max_measure_time_ms < 1 {
+            return Err("MaxMeasureTimeMs Должен быть больше 0");
+        }
+        config.max_measure_time_ms = max_measure_time_ms;
+        modified = true;
+    }
+
+    if let Some(settling_samples) = input.settling_samples {
+        config.settling_samples = settling_samples;
+        modified = true;
+    }
+
+    if let Some(settling_time_ms) = input.settling_time_ms {
+        config.settling_time_ms = settling_time_ms;
+        modified = true;
+    }
+
+    if let Some(first_switch_timeout_ms) = input.first_switch_timeout_ms {
+        config.first_switch_timeout_ms = first_switch_timeout_ms;
+        modified = true;
+    }
+
+    if let Some(phase_timeout_ms) = input.phase_timeout_ms {
+        config.phase_timeout_ms = phase_timeout_ms;
+        modified = true;
+    }
+
+    if let Some(measure_timeout_ms) = input.measure_timeout_ms {
+        config.measure_timeout_ms = measure_timeout_ms;
+        modified = true;
+    }
+
+    // Переходный процесс должен заканчиваться раньше фазы и минимального времени измерения
+    if input.settling_time_ms.is_some()
+        || input.phase_timeout_ms.is_some()
+        || input.measure_duration_ms.is_some()
+        || input.measure_strategy.is_some()
+    {
+        if config.phase_timeout_ms > 0 && config.settling_time_ms >= config.phase_timeout_ms {
+            return Err("SettlingTimeMs Должен быть меньше PhaseTimeoutMs");
+        }
+        if config.measure_strategy == MeasureStrategyKind::Duration
+            && config.settling_time_ms >= config.measure_duration_ms
+        {
+            return Err("SettlingTimeMs Должен быть меньше MeasureDurationMs");
+        }
+    }
+
+    if let Some(quantile_method) = input.quantile_method {
+        config.quantile_method = quantile_method;
+        modified = true;
+    }
+
+    if let Some(fence_multiplier) = input.fence_multiplier {
+        if fence_multiplier.is_nan() || fence_multiplier < 0.0 {
+            return Err("FenceMultiplier Не может быть отрицательным");
+        }
+        config.fence_multiplier = fence_multiplier;
+        modified = true;
+    }
+
+    if let Some(confidence_level) = input.confidence_level {
+        if confidence_level.is_nan() || confidence_level <= 0.0 || confidence_level >= 1.0 {
+            return Err("ConfidenceLevel Должен быть в диапазоне (0; 1)");
+        }
+        config.confidence_level = confidence_level;
+        modified = true;
+    }
+
+    if let Some(outlier_rejection) = input.outlier_rejection {
+        config.outlier_rejection = outlier_rejection;
+        modified = true;
+    }
+
+    if let Some(outlier_multiplier) = input.outlier_multiplier {
+        if outlier_multiplier.is_nan() || outlier_multiplier <= 0.0 {
+            return Err("OutlierMultiplier Должен быть больше 0");
+        }
+        config.outlier_multiplier = outlier_multiplier;
+        modified = true;
+    }
+
+    if let Some(measure_retries) = input.measure_retries {
+        config.measure_retries = measure_retries;
+        modified = true;
+    }
+
+    if let Some(retry_freq_iqr) = input.retry_freq_iqr {
+        if retry_freq_iqr.is_nan() || retry_freq_iqr <= 0.0 {
+            return Err("RetryFreqIqr Должен быть больше 0");
+        }
+        config.retry_freq_iqr = retry_freq_iqr;
+        modified = true;
+    }
+
+    if let Some(retry_rk_iqr) = input.retry_rk_iqr {
+        if retry_rk_iqr.is_nan() || retry_rk_iqr <= 0.0 {
+            return Err("RetryRkIqr Должен быть больше 0");
+        }
+        config.retry_rk_iqr = retry_rk_iqr;
+        modified = true;
+    }
+
+    // Пределы проверяются вместе: запрос может сдвигать оба
+    if input.auto_freq_min.is_some() || input.auto_freq_max.is_some() {
+        let auto_freq_min = input.auto_freq_min.unwrap_or(config.auto_freq_min);
+        let auto_freq_max = input.auto_freq_max.unwrap_or(config.auto_freq_max);
+        if auto_freq_min.is_nan() || auto_freq_min <= 0.0 {
+            return Err("AutoFreqMin Должна быть больше 0");
+        }
+        if auto_freq_max.is_nan() || auto_freq_max <= auto_freq_min {
+            return Err("AutoFreqMax Должна быть больше AutoFreqMin");
+        }
+        config.auto_freq_min = auto_freq_min;
+        config.auto_freq_max = auto_freq_max;
+        modified = true;
+    }
+
+    if let Some(auto_rk_max) = input.auto_rk_max {
+        if auto_rk_max.is_nan() || auto_rk_max <= 0.0 {
+            return Err("AutoRkMax Должно быть больше 0");
+        }
+        config.auto_rk_max = auto_rk_max;
+        modified = true;
+    }
+
+    if let Some(auto_confirm_samples) = input.auto_confirm_samples {
+        if auto_confirm_samples < 1 {
+            return Err("AutoConfirmSamples Должен быть больше 0");
+        }
+        config.auto_confirm_samples = auto_confirm_samples;
+        modified = true;
+    }
+
+    Ok(modified)
 }
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+
+use clicker_data_collector::{
+    Capture, Clicker, ClickerController, Config, ConnectionState, Diagnostics, EmulationProfile,
+    FakeClicker, InjectedError,
+};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::CLICKER_TIMEOUT;
+
+/// Имя порта, вместо устройства на котором работает эмуляция по профилю Emulation
+pub(crate) const FAKE_PORT: &str = "fake";
+
+#[derive(Serialize)]
+struct DeviceStatus {
+    port: String,
+    #[serde(flatten)]
+    connection: ConnectionState,
+    diagnostics: Diagnostics,
+}
+
+/// Подключить устройство на порту `port`, при наличии `capture` обмен записывается в него
+pub(crate) fn attach_clicker(
+    clicker_ctrl: &mut ClickerController,
+    port: String,
+    retries: u32,
+    capture: Option<Capture>,
+) {
+    tracing::info!("Attaching clicker on {}", port);
+    clicker_ctrl.attach(move || {
+        let port = port.clone();
+        let capture = capture.clone();
+        async move {
+            let mut clicker = Clicker::new(&port, CLICKER_TIMEOUT, retries)
+                .await?
+                .with_capture(capture);
+            clicker.test().await?;
+            Ok(clicker)
+        }
+    });
+}
+
+/// Подключить эмулятор устройства с профилем `profile`
+pub(crate) fn attach_fake(clicker_ctrl: &mut ClickerController, profile: EmulationProfile) {
+    tracing::info!("Attaching emulated clicker: {}", profile);
+    clicker_ctrl.attach(move || {
+        let profile = profile.clone();
+        async move { Ok::<_, InjectedError>(FakeClicker::from_profile(profile)) }
+    });
+}
+
+async fn device_status(
+    config: &Mutex<Config>,
+    clicker_ctrl: &Mutex<ClickerController>,
+) -> Json<DeviceStatus> {
+    let port = config.lock().await.rk_meter_port.clone();
+    let guard = clicker_ctrl.lock().await;
+    Json(DeviceStatus {
+        port,
+        connection: guard.connection_state(),
+        diagnostics: guard.diagnostics(),
+    })
+}
+
+// Состояние подключения к устройству
+pub(crate) async fn handle_device_get(
+    State(config): State<Arc<Mutex<Config>>>,
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+) -> impl IntoResponse {
+    device_status(&config, &clicker_ctrl).await
+}
+
+// Подключить устройство на настроенном порту
+pub(crate) async fn handle_device_connect(
+    State(config): State<Arc<Mutex<Config>>>,
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+    State(capture): State<Option<Capture>>,
+) -> impl IntoResponse {
+    let (port, retries, profile) = {
+        let config = config.lock().await;
+        (
+            config.rk_meter_port.clone(),
+            config.retries,
+            config.emulation.clone(),
+        )
+    };
+    tracing::debug!("handle_device_connect: {}", port);
+
+    {
+        let mut guard = clicker_ctrl.lock().await;
+        guard.interrupt_mesure().await;
+        if port == FAKE_PORT {
+            attach_fake(&mut guard, profile);
+        } else {
+            attach_clicker(&mut guard, port, retries, capture);
+        }
+    }
+
+    device_status(&config, &clicker_ctrl).await
+}
+
+// Отключить устройство
+pub(crate) async fn handle_device_disconnect(
+    State(config): State<Arc<Mutex<Config>>>,
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+) -> impl IntoResponse {
+    tracing::debug!("handle_device_disconnect");
+
+    {
+        let mut guard = clicker_ctrl.lock().await;
+        guard.interrupt_mesure().await;
+        guard.detach();
+    }
+
+    device_status(&config, &clicker_ctrl).await
+}
+
+// Проверить связь с устройством на настроенном порту
+pub(crate) async fn handle_device_test(
+    State(config): State<Arc<Mutex<Config>>>,
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+) -> impl IntoResponse {
+    let (port, retries) = {
+        let config = config.lock().await;
+        (config.rk_meter_port.clone(), config.retries)
+    };
+    tracing::debug!("handle_device_test: {}", port);
+
+    if clicker_ctrl.lock().await.connection_state() == ConnectionState::Connected {
+        return (StatusCode::OK, "Устройство подключено".to_string());
+    }
+
+    if port == FAKE_PORT {
+        return (StatusCode::OK, "Эмуляция устройства".to_string());
+    }
+
+    match Clicker::connect(port.clone(), CLICKER_TIMEOUT, retries).await {
+        Ok(_) => (StatusCode::OK, format!("Устройство на порту {port} отвечает")),
+        Err(e) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Устройство на порту {port} не отвечает: {e:?}"),
+        ),
+    }
+}
+
+// Список последовательных портов системы
+pub(crate) async fn handle_ports_get() -> impl IntoResponse {
+    match clicker_data_collector::available_ports() {
+        Ok(ports) => Json(ports).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Не удалось получить список портов: {e:?}"),
+        )
+            .into_response(),
+    }
+}
+
+// Список последовательных портов системы с проверкой наличия на них устройства
+pub(crate) async fn handle_ports_probe(
+    State(config): State<Arc<Mutex<Config>>>,
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+) -> impl IntoResponse {
+    let ports = match clicker_data_collector::available_ports() {
+        Ok(ports) => ports,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Не удалось получить список портов: {e:?}"),
+            )
+                .into_response()
+        }
+    };
+
+    // Порт подключенного устройства занят, проверять его не нужно
+    let connected_port =
+        if clicker_ctrl.lock().await.connection_state() == ConnectionState::Connected {
+            Some(config.lock().await.rk_meter_port.clone())
+        } else {
+            None
+        };
+
+    let (mut connected, to_probe): (Vec<_>, Vec<_>) = ports
+        .into_iter()
+        .partition(|p| Some(&p.name) == connected_port.as_ref());
+    connected.iter_mut().for_each(|p| p.is_clicker = Some(true));
+
+    let mut ports = clicker_data_collector::probe_ports(to_probe, CLICKER_TIMEOUT).await;
+    ports.append(&mut connected);
+    ports.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Json(ports).into_response()
+}
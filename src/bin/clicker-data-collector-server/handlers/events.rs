@@ -0,0 +1,65 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::State,
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Sse,
+    },
+};
+
+use clicker_data_collector::{ClickerController, ControllerEvent};
+use tokio::sync::{broadcast, Mutex};
+
+/// Поток событий контроллера до закрытия канала, пропущенные отстающим подписчиком события отбрасываются
+fn events(
+    mut events_rx: broadcast::Receiver<ControllerEvent>,
+) -> impl futures::Stream<Item = ControllerEvent> {
+    async_stream::stream! {
+        loop {
+            match events_rx.recv().await {
+                Ok(event) => yield event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("Controller events subscriber lagged, {} events lost", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+/// Писать события контроллера в журнал
+pub(crate) fn spawn_event_log(events_rx: broadcast::Receiver<ControllerEvent>) {
+    tokio::spawn(async move {
+        use futures::StreamExt;
+
+        let mut events = Box::pin(events(events_rx));
+        while let Some(event) = events.next().await {
+            match &event {
+                ControllerEvent::MeasureInterrupted { .. }
+                | ControllerEvent::DeviceError { .. } => {
+                    tracing::warn!("Controller event: {:?}", event)
+                }
+                _ => tracing::debug!("Controller event: {:?}", event),
+            }
+        }
+    });
+}
+
+// Поток событий измерений и подключения к устройству (Server-Sent Events)
+pub(crate) async fn handle_events(
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+) -> impl IntoResponse {
+    use futures::StreamExt;
+
+    let events_rx = clicker_ctrl.lock().await.subscribe_events();
+    let stream = events(events_rx).map(|event| {
+        Ok::<_, Infallible>(
+            Event::default()
+                .json_data(event)
+                .unwrap_or_else(|_| Event::default().comment("serialization error")),
+        )
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -8,11 +8,16 @@ use axum::{
 };
 
 use clicker_data_collector::{
-    data_model::{DataModel, ResonatorData},
-    ClickerController, MeasureProcessStat,
+    data_model::{DataModel, FrequencyOffset, ResonatorData},
+    AttemptStat, BestAttempt, ClickerController, Config, InterruptReason, MeasureFeed,
+    MeasureProcessStat, MeasureProcessState, Repeatability, ResonatorCatalog, Segment, TimedSample,
+    Verdict,
+};
+use serde::Serialize;
+use tokio::{
+    sync::{watch::Receiver, Mutex},
+    task::JoinHandle,
 };
-use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
 
 // Получить список всех резонаторов
 pub(crate) async fn handle_measurements_get(
@@ -22,19 +27,38 @@ pub(crate) async fn handle_measurements_get(
     struct Model {
         records: Vec<ResData>,
         total: usize,
+        /// Среднее отклонение частоты партии от номинала
+        mean_offset: Option<FrequencyOffset>,
     }
 
     #[allow(non_snake_case)]
-    #[derive(Serialize, Deserialize)]
+    #[derive(Serialize)]
     struct ResData {
         id: u32,
         timestamp: String,
         F: f32,
         F_deviation: f32,
+        F_offset: Option<f32>,
+        F_offset_ppm: Option<f32>,
         Freqs: Vec<f32>,
+        F_raw: f32,
+        F_raw_deviation: f32,
+        RejectedFreqs: Vec<usize>,
         Rk: f32,
         Rk_deviation: f32,
         Rks: Vec<f32>,
+        Rk_raw: f32,
+        Rk_raw_deviation: f32,
+        RejectedRks: Vec<usize>,
+        DiscardedFreqs: Vec<f32>,
+        DiscardedRks: Vec<f32>,
+        Segments: Vec<Segment>,
+        Samples: Vec<TimedSample>,
+        F_repeatability: Option<Repeatability>,
+        Rk_repeatability: Option<Repeatability>,
+        Verdict: Option<Verdict>,
+        Attempts: u32,
+        Unstable: bool,
         Comment: String,
     }
 
@@ -46,10 +70,27 @@ pub(crate) async fn handle_measurements_get(
                 timestamp: format!("/Date({})/", data.timestamp.timestamp_millis()),
                 F: data.frequency,
                 F_deviation: data.frequency_deviation,
+                F_offset: data.frequency_offset.map(|o| o.hz),
+                F_offset_ppm: data.frequency_offset.map(|o| o.ppm),
                 Freqs: data.freqs.clone(),
+                F_raw: data.raw_frequency,
+                F_raw_deviation: data.raw_frequency_deviation,
+                RejectedFreqs: data.rejected_freqs.clone(),
                 Rk: data.rk,
                 Rk_deviation: data.rk_deviation,
                 Rks: data.rks.clone(),
+                Rk_raw: data.raw_rk,
+                Rk_raw_deviation: data.raw_rk_deviation,
+                RejectedRks: data.rejected_rks.clone(),
+                DiscardedFreqs: data.discarded_freqs.clone(),
+                DiscardedRks: data.discarded_rks.clone(),
+                Segments: data.segments.clone(),
+                Samples: data.samples.clone(),
+                F_repeatability: data.frequency_repeatability,
+                Rk_repeatability: data.rk_repeatability,
+                Verdict: data.verdict.clone(),
+                Attempts: data.attempts,
+                Unstable: data.unstable,
                 Comment: data.comment.clone(),
             }
         }
@@ -57,6 +98,7 @@ pub(crate) async fn handle_measurements_get(
 
     let data_model_guard = data_model.lock().await;
     let total = data_model_guard.resonators.len();
+    let mean_offset = data_model_guard.mean_offset();
 
     Json(Model {
         records: data_model_guard
@@ -70,79 +112,221 @@ pub(crate) async fn handle_measurements_get(
             })
             .collect::<Vec<_>>(),
         total,
+        mean_offset,
     })
 }
 
-async fn measure_common<F: Fn(&mut DataModel, MeasureProcessStat) + Send + 'static>(
+/// Запустить измерение, если после подписки `cancel_rx` не было отмены
+async fn start_measure(
+    clicker_ctrl: &Mutex<ClickerController>,
+    config: &Config,
+    cancel_rx: &Receiver<()>,
+) -> Result<Receiver<MeasureProcessStat>, String> {
+    let mut guard = clicker_ctrl.lock().await;
+    if cancel_rx.has_changed().unwrap_or(true) {
+        return Err("Измерение отменено".to_string());
+    }
+    guard.set_timeouts(config.timeouts());
+    guard.start_mesure_with(
+        config.measure_strategy(),
+        config.settling(),
+        config.statistics(),
+    )?;
+    guard
+        .subscribe_measure_status()
+        .ok_or_else(|| "Не удалось запустить измерительный процесс!".to_string())
+}
+
+/// Запустить измерение с повтором при большом разбросе, ход измерения публикуется в `feed`.
+/// Результат, в том числе прерванного измерения, передается в `after_measure`
+/// до публикации последнего состояния
+pub(crate) async fn start_measure_task<F: Fn(&mut DataModel, ResonatorData) + Send + 'static>(
+    config: Arc<Mutex<Config>>,
     data_model: Arc<Mutex<DataModel>>,
+    catalog: Arc<Mutex<ResonatorCatalog>>,
     clicker_ctrl: Arc<Mutex<ClickerController>>,
+    feed: MeasureFeed,
     after_measure: F,
-) -> axum::response::Response {
-    use clicker_data_collector::MeasureProcessState;
+) -> Result<JoinHandle<()>, String> {
+    let config = config.lock().await.clone();
+    let retry = config.retry_policy();
 
-    let rx = {
-        let mut guard = clicker_ctrl.lock().await;
-        match guard.start_mesure() {
-            Ok(_) => guard.subscribe_measure_status(),
-            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
-        }
-    };
+    let cancel_rx = clicker_ctrl.lock().await.subscribe_cancel();
+    let mut rx = start_measure(&clicker_ctrl, &config, &cancel_rx).await?;
+
+    let attempts = retry.attempts();
+    Ok(tokio::spawn(async move {
+        let mut best = BestAttempt::new(retry);
+
+        for attempt in 1..=attempts {
+            let mut res = loop {
+                if rx.changed().await.is_err() {
+                    // результат прерванной попытки и лучшей из прежних сохраняется как обычно
+                    let mut stat = (*rx.borrow()).clone();
+                    stat.state = MeasureProcessState::Interrupted(InterruptReason::ChannelClosed);
+                    break stat;
+                }
+                let res = (*rx.borrow()).clone();
+                match res.state {
+                    MeasureProcessState::Idle => continue,
+                    MeasureProcessState::Running => {
+                        feed.publish(AttemptStat {
+                            attempt,
+                            attempts,
+                            retry: false,
+                            unstable: false,
+                            stat: res,
+                        });
+                    }
+                    MeasureProcessState::Interrupted(_) | MeasureProcessState::Finished => {
+                        break res
+                    }
+                }
+            };
 
-    match rx {
-        Some(mut rx) => {
-            let stream = async_stream::stream! {
-                let res = loop {
-                    match rx.changed().await {
-                        Ok(_) => {
-                            let res = (*rx.borrow()).clone();
-                            match res.state {
-                                MeasureProcessState::Idle => {
-                                    continue;
-                                }
-                                MeasureProcessState::Running => {
-                                    yield res;
-                                }
-                                MeasureProcessState::Interrupted | MeasureProcessState::Finished => {
-                                    yield res.clone();
-                                    break res;
-                                }
-                            }
+            let mut excess = f32::INFINITY;
+            match ResonatorData::try_from(res.clone()) {
+                Ok(mut resonator) => {
+                    resonator.attempts = attempt;
+                    let (freq_iqr, rk_iqr) =
+                        (resonator.frequency_deviation, resonator.rk_deviation);
+                    excess = best.push(resonator, freq_iqr, rk_iqr);
+                }
+                Err(e) => tracing::error!("Попытка {attempt} без результата: {e}"),
+            }
 
+            if res.state == MeasureProcessState::Finished && best.need_retry(attempt, excess) {
+                match start_measure(&clicker_ctrl, &config, &cancel_rx).await {
+                    Ok(new_rx) => {
+                        tracing::info!(
+                            "Разброс превышает пороги, повтор измерения {}/{}",
+                            attempt + 1,
+                            attempts
+                        );
+                        rx = new_rx;
+                        feed.publish(AttemptStat {
+                            attempt,
+                            attempts,
+                            retry: true,
+                            unstable: false,
+                            stat: res,
+                        });
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::error!("Не удалось повторить измерение: {e}");
+                        if cancel_rx.has_changed().unwrap_or(true) {
+                            res.state = MeasureProcessState::Interrupted(InterruptReason::Canceled);
                         }
-                        Err(_) => return,
                     }
-                };
+                }
+            }
 
+            let mut unstable = false;
+            if let Some((mut resonator, is_unstable)) =
+                best.take(res.state == MeasureProcessState::Finished)
+            {
+                unstable = is_unstable;
+                resonator.unstable = unstable;
                 let mut guard = data_model.lock().await;
-                after_measure(&mut guard, res);
-            };
-            axum_streams::StreamBodyAs::json_nl(stream).into_response()
+                guard.evaluate_resonator(&mut resonator, &*catalog.lock().await);
+                after_measure(&mut guard, resonator);
+            }
+            feed.publish(AttemptStat {
+                attempt,
+                attempts,
+                retry: false,
+                unstable,
+                stat: res,
+            });
+            break;
         }
-        None => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Не удалось запустить измерительный процесс!",
-        )
-            .into_response(),
+    }))
+}
+
+async fn measure_common<F: Fn(&mut DataModel, ResonatorData) + Send + 'static>(
+    config: Arc<Mutex<Config>>,
+    data_model: Arc<Mutex<DataModel>>,
+    catalog: Arc<Mutex<ResonatorCatalog>>,
+    clicker_ctrl: Arc<Mutex<ClickerController>>,
+    feed: MeasureFeed,
+    after_measure: F,
+) -> axum::response::Response {
+    // подписка до запуска, чтобы не пропустить первые состояния
+    let stream = feed.stream(false);
+    match start_measure_task(
+        config,
+        data_model,
+        catalog,
+        clicker_ctrl,
+        feed,
+        after_measure,
+    )
+    .await
+    {
+        Ok(_) => axum_streams::StreamBodyAs::json_nl(stream).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+// Состояние текущего или последнего измерения
+pub(crate) async fn handle_measure_state(State(feed): State<MeasureFeed>) -> impl IntoResponse {
+    #[derive(Serialize)]
+    struct MeasureState {
+        /// Измерение идет, к нему можно подключиться
+        running: bool,
+        #[serde(flatten)]
+        current: Option<AttemptStat>,
+    }
+
+    Json(MeasureState {
+        running: feed.is_running(),
+        current: feed.current(),
+    })
+}
+
+// Подключиться к ходу текущего измерения, запущенного другим клиентом
+pub(crate) async fn handle_measure_progress(State(feed): State<MeasureFeed>) -> impl IntoResponse {
+    tracing::debug!("handle_measure_progress");
+
+    match feed.progress() {
+        Some(stream) => axum_streams::StreamBodyAs::json_nl(stream).into_response(),
+        None => (StatusCode::NOT_FOUND, "Нет запущенного измерения").into_response(),
     }
 }
 
+/// Добавить резонатор в конец таблицы
+pub(crate) fn append_resonator(data_model: &mut DataModel, new_res: ResonatorData) {
+    data_model.resonators.push(new_res);
+}
+
 // Начать процедуру измерения нового резонатора
 pub(crate) async fn handle_measurements_append(
+    State(config): State<Arc<Mutex<Config>>>,
     State(data_model): State<Arc<Mutex<DataModel>>>,
+    State(catalog): State<Arc<Mutex<ResonatorCatalog>>>,
     State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+    State(feed): State<MeasureFeed>,
 ) -> impl IntoResponse {
     tracing::debug!("handle_measurements_add");
 
-    let after_measure = |data_model: &mut DataModel, mr: MeasureProcessStat| {
-        data_model.resonators.push(mr.into());
-    };
-
-    measure_common(data_model, clicker_ctrl, after_measure).await
+    measure_common(
+        config,
+        data_model,
+        catalog,
+        clicker_ctrl,
+        feed,
+        append_resonator,
+    )
+    .await
 }
 
 pub(crate) async fn handle_measurements_insert(
+    State(config): State<Arc<Mutex<Config>>>,
     State(data_model): State<Arc<Mutex<DataModel>>>,
+    State(catalog): State<Arc<Mutex<ResonatorCatalog>>>,
     State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+    State(feed): State<MeasureFeed>,
     Path(id): Path<u32>,
     body: String,
 ) -> impl IntoResponse {
@@ -157,9 +341,7 @@ pub(crate) async fn handle_measurements_insert(
         id
     };
 
-    let after_measure = move |data_model: &mut DataModel, mr: MeasureProcessStat| {
-        let new_res = mr.into();
-
+    let after_measure = move |data_model: &mut DataModel, new_res: ResonatorData| {
         if insert {
             data_model.resonators.insert(id as usize, new_res);
         } else {
@@ -167,7 +349,15 @@ pub(crate) async fn handle_measurements_insert(
         }
     };
 
-    measure_common(data_model, clicker_ctrl, after_measure).await
+    measure_common(
+        config,
+        data_model,
+        catalog,
+        clicker_ctrl,
+        feed,
+        after_measure,
+    )
+    .await
 }
 
 // Перезапустить измерение существующего резонатора id
@@ -4,7 +4,7 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_template::{Key, RenderHtml};
 
 use chrono::Local;
-use clicker_data_collector::data_model::DataModel;
+use clicker_data_collector::{data_model::DataModel, ResonatorCatalog};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
@@ -17,6 +17,9 @@ pub(crate) struct ReportHeader {
     pub ambient_temperature_range: String,
     pub comment: String,
     pub date: String,
+    /// Номинальная частота партии, Гц, пусто - из каталога по типу партии
+    #[serde(default)]
+    pub nominal_freq: String,
 }
 
 pub(crate) async fn handle_get_work(State(engine): State<AppEngine>) -> impl IntoResponse {
@@ -46,6 +49,10 @@ pub(crate) async fn handle_get_globals(
         ambient_temperature_range,
         comment: data_model.comment.clone(),
         date: data_model.timestamp.format("%Y-%m-%d").to_string(),
+        nominal_freq: data_model
+            .nominal_freq
+            .map(|v| v.to_string())
+            .unwrap_or_default(),
     };
 
     Json(report_header)
@@ -53,12 +60,17 @@ pub(crate) async fn handle_get_globals(
 
 pub(crate) async fn handle_set_globals(
     State(data_model): State<Arc<Mutex<DataModel>>>,
+    State(catalog): State<Arc<Mutex<ResonatorCatalog>>>,
     Json(payload): Json<ReportHeader>,
 ) -> impl IntoResponse {
-    if let Err(e) = try_parce_config(payload, data_model.lock().await.borrow_mut()) {
+    let mut data_model = data_model.lock().await;
+    if let Err(e) = try_parce_config(payload, data_model.borrow_mut()) {
         return (StatusCode::BAD_REQUEST, e).into_response();
     }
 
+    // Тип и номинал партии могли измениться
+    data_model.evaluate(&*catalog.lock().await);
+
     return StatusCode::OK.into_response();
 }
 
@@ -69,6 +81,7 @@ pub(crate) async fn handle_generate_report_excel(
     use super::into_body::IntoBody;
 
     const SHEET_NAME: &str = "report";
+    const SAMPLES_SHEET_NAME: &str = "samples";
     let report_template_xlsx = include_bytes!("report.xlsx");
 
     if let Ok(mut book) =
@@ -132,13 +145,36 @@ pub(crate) async fn handle_generate_report_excel(
                 borders.set_right(style);
             }
 
-            let resonators = data_model.lock().await.resonators.clone();
+            let (resonators, mean_offset) = {
+                let guard = data_model.lock().await;
+                (guard.resonators.clone(), guard.mean_offset())
+            };
 
             if !resonators.is_empty() {
                 book.insert_new_row(SHEET_NAME, &9, &(resonators.len() as u32));
 
                 let sheet = book.get_sheet_by_name_mut(SHEET_NAME).unwrap();
 
+                // Дополнительные колонки в оформлении заголовка шаблона
+                const EXTRA_HEADER: [&str; 10] = [
+                    "F по всем отсчетам, Гц",
+                    "Rk по всем отсчетам, кОм",
+                    "Выбросов F",
+                    "Выбросов Rk",
+                    "Годен",
+                    "Причина",
+                    "Отклонение F, Гц",
+                    "Отклонение F, ppm",
+                    "Попыток измерения",
+                    "Нестабилен",
+                ];
+                let header_style = sheet.get_style((5, 8)).clone();
+                EXTRA_HEADER.iter().enumerate().for_each(|(i, title)| {
+                    let col = 6 + i as u32;
+                    sheet.get_cell_value_mut((col, 8)).set_value_string(*title);
+                    sheet.set_style((col, 8), header_style.clone());
+                });
+
                 resonators.iter().enumerate().for_each(|(i, row)| {
                     let row_num = 9 + i as u32;
 
@@ -158,7 +194,128 @@ pub(crate) async fn handle_generate_report_excel(
                         .get_cell_value_mut((5, row_num))
                         .set_value_string(&row.comment);
                     set_borders(sheet, (5, row_num));
+                    sheet
+                        .get_cell_value_mut((6, row_num))
+                        .set_value_number(row.raw_frequency);
+                    set_borders(sheet, (6, row_num));
+                    sheet
+                        .get_cell_value_mut((7, row_num))
+                        .set_value_number(row.raw_rk);
+                    set_borders(sheet, (7, row_num));
+                    sheet
+                        .get_cell_value_mut((8, row_num))
+                        .set_value_number(row.rejected_freqs.len() as u32);
+                    set_borders(sheet, (8, row_num));
+                    sheet
+                        .get_cell_value_mut((9, row_num))
+                        .set_value_number(row.rejected_rks.len() as u32);
+                    set_borders(sheet, (9, row_num));
+                    let (passed, reason) = match &row.verdict {
+                        Some(v) if v.passed => ("Да", String::new()),
+                        Some(v) => ("Нет", v.reasons.join("; ")),
+                        None => ("---", "Тип резонатора не найден в каталоге".to_string()),
+                    };
+                    sheet
+                        .get_cell_value_mut((10, row_num))
+                        .set_value_string(passed);
+                    set_borders(sheet, (10, row_num));
+                    sheet
+                        .get_cell_value_mut((11, row_num))
+                        .set_value_string(reason);
+                    set_borders(sheet, (11, row_num));
+                    if let Some(offset) = row.frequency_offset {
+                        sheet
+                            .get_cell_value_mut((12, row_num))
+                            .set_value_number(offset.hz);
+                        sheet
+                            .get_cell_value_mut((13, row_num))
+                            .set_value_number(offset.ppm);
+                    }
+                    set_borders(sheet, (12, row_num));
+                    set_borders(sheet, (13, row_num));
+                    sheet
+                        .get_cell_value_mut((14, row_num))
+                        .set_value_number(row.attempts);
+                    set_borders(sheet, (14, row_num));
+                    sheet
+                        .get_cell_value_mut((15, row_num))
+                        .set_value_string(if row.unstable { "Да" } else { "" });
+                    set_borders(sheet, (15, row_num));
                 });
+
+                // Среднее отклонение партии под таблицей
+                if let Some(offset) = mean_offset {
+                    let row_num = 9 + resonators.len() as u32;
+                    sheet
+                        .get_cell_value_mut((11, row_num))
+                        .set_value_string("Среднее по партии");
+                    sheet
+                        .get_cell_value_mut((12, row_num))
+                        .set_value_number(offset.hz);
+                    sheet
+                        .get_cell_value_mut((13, row_num))
+                        .set_value_number(offset.ppm);
+                    (11..=13).for_each(|col| set_borders(sheet, (col, row_num)));
+                }
+            }
+        }
+
+        // Все отсчеты с временем получения
+        if let Ok(sheet) = book.new_sheet(SAMPLES_SHEET_NAME) {
+            const HEADER: [&str; 7] = [
+                "№",
+                "Режим",
+                "Время",
+                "От начала измерения, мс",
+                "Значение",
+                "Статус",
+                "Интервал, мс",
+            ];
+            HEADER.iter().enumerate().for_each(|(col, title)| {
+                sheet
+                    .get_cell_value_mut((col as u32 + 1, 1))
+                    .set_value_string(*title);
+            });
+
+            let resonators = data_model.lock().await.resonators.clone();
+            let mut row_num = 2;
+            for (i, resonator) in resonators.iter().enumerate() {
+                let mut prev_offset = None;
+                for sample in &resonator.samples {
+                    let time = chrono::DateTime::from_timestamp_millis(sample.unix_ms as i64)
+                        .map(|t| {
+                            t.with_timezone(&chrono::Local)
+                                .format("%H:%M:%S%.3f")
+                                .to_string()
+                        })
+                        .unwrap_or_default();
+
+                    sheet
+                        .get_cell_value_mut((1, row_num))
+                        .set_value_number(i as u32 + 1);
+                    sheet
+                        .get_cell_value_mut((2, row_num))
+                        .set_value_string(format!("{:?}", sample.mode));
+                    sheet
+                        .get_cell_value_mut((3, row_num))
+                        .set_value_string(time);
+                    sheet
+                        .get_cell_value_mut((4, row_num))
+                        .set_value_number(sample.offset_ms);
+                    sheet
+                        .get_cell_value_mut((5, row_num))
+                        .set_value_number(sample.value);
+                    sheet
+                        .get_cell_value_mut((6, row_num))
+                        .set_value_string(format!("{:?}", sample.status));
+                    if let Some(prev) = prev_offset {
+                        sheet
+                            .get_cell_value_mut((7, row_num))
+                            .set_value_number(sample.offset_ms - prev);
+                    }
+                    prev_offset = Some(sample.offset_ms);
+                    row_num += 1;
+                }
             }
         }
 
@@ -217,6 +374,14 @@ fn try_parce_config(payload: ReportHeader, data_model: &mut DataModel) -> Result
 
     data_model.comment = payload.comment;
 
+    data_model.nominal_freq = match payload.nominal_freq.trim() {
+        "" => None,
+        s => match s.parse::<f32>() {
+            Ok(v) if v > 0.0 => Some(v),
+            _ => return Err(format!("Неверная номинальная частота '{}'", s)),
+        },
+    };
+
     data_model.timestamp = dateparser::parse_with_timezone(&payload.date, &Local)
         .map_err(|_| format!("Значение '{}' не содержит дату", payload.date))?
         .into();
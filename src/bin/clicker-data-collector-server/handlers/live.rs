@@ -0,0 +1,53 @@
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive},
+        IntoResponse, Sse,
+    },
+};
+
+use clicker_data_collector::ClickerController;
+use futures::StreamExt;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+/// Отсчетов в секунду по умолчанию
+const DEFAULT_RATE: f32 = 5.0;
+/// Наименьшая частота отсчетов: меньшие значения увеличиваются до нее
+const MIN_RATE: f32 = 0.1;
+/// Наибольшая частота отсчетов, которую может запросить клиент
+const MAX_RATE: f32 = 50.0;
+
+#[derive(Deserialize)]
+pub(crate) struct LiveParams {
+    /// Отсчетов в секунду
+    rate: Option<f32>,
+}
+
+// Поток текущих отсчетов устройства (Server-Sent Events), не чаще rate в секунду
+pub(crate) async fn handle_live(
+    State(clicker_ctrl): State<Arc<Mutex<ClickerController>>>,
+    Query(params): Query<LiveParams>,
+) -> impl IntoResponse {
+    let rate = params.rate.unwrap_or(DEFAULT_RATE);
+    if rate.is_nan() || rate <= 0.0 {
+        return (StatusCode::BAD_REQUEST, "rate Должен быть больше 0").into_response();
+    }
+    let min_interval = Duration::from_secs_f32(1.0 / rate.clamp(MIN_RATE, MAX_RATE));
+    tracing::debug!("handle_live: interval={:?}", min_interval);
+
+    let stream = clicker_ctrl.lock().await.live(min_interval).map(|reading| {
+        Ok::<_, Infallible>(
+            Event::default()
+                .json_data(reading)
+                .unwrap_or_else(|_| Event::default().comment("serialization error")),
+        )
+    });
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
@@ -1,13 +1,27 @@
+pub mod auto_measure;
+pub mod catalog;
 pub mod config;
+pub mod device;
+pub mod events;
 pub mod handle_control;
 pub mod handle_work;
 pub mod into_body;
+pub mod live;
 pub mod static_files;
 
+pub(crate) use auto_measure::{handle_auto_get, handle_auto_put};
+pub(crate) use catalog::{handle_catalog_delete, handle_catalog_get, handle_catalog_put};
 pub(crate) use config::{handle_config, handle_config_and_save};
+pub(crate) use device::{
+    handle_device_connect, handle_device_disconnect, handle_device_get, handle_device_test,
+    handle_ports_get, handle_ports_probe,
+};
+pub(crate) use events::handle_events;
+pub(crate) use live::handle_live;
 pub(crate) use handle_control::{
     handle_measurements_delete, handle_measurements_get, handle_measurements_append,
-    handle_measurements_put, handle_measurements_insert, handle_measurements_cancel
+    handle_measurements_put, handle_measurements_insert, handle_measurements_cancel,
+    handle_measure_state, handle_measure_progress
 };
 pub(crate) use handle_work::{
     handle_generate_report_excel, handle_get_globals, handle_set_globals, handle_get_work,
@@ -1,13 +1,14 @@
 #![feature(async_iterator)]
 
+mod cli;
 mod handlers;
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use axum::{
     extract::FromRef,
     response::Redirect,
-    routing::{get, put},
+    routing::{delete, get, post, put},
     Router,
 };
 
@@ -18,6 +19,7 @@ use tower_http::trace::TraceLayer;
 use tracing_subscriber::prelude::*;
 
 use axum_template::engine::Engine;
+use clap::Parser;
 
 use minijinja::Environment;
 
@@ -25,14 +27,21 @@ use handlers::*;
 
 pub(crate) type AppEngine = Engine<Environment<'static>>;
 
+/// Таймаут ожидания ответа от устройства
+pub(crate) const CLICKER_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(250);
+
 #[derive(Clone, FromRef)]
 struct AppState {
     engine: AppEngine,
-    config: clicker_data_collector::Config,
+    config: Arc<Mutex<clicker_data_collector::Config>>,
     config_file: std::path::PathBuf,
 
     data_model: Arc<Mutex<DataModel>>,
+    catalog: Arc<Mutex<clicker_data_collector::ResonatorCatalog>>,
+    catalog_file: catalog::CatalogFile,
     clicker_ctrl: Arc<Mutex<clicker_data_collector::ClickerController>>,
+    measure_feed: clicker_data_collector::MeasureFeed,
+    capture: Option<clicker_data_collector::Capture>,
 }
 
 fn float2dgt(value: String) -> String {
@@ -45,41 +54,127 @@ fn float2dgt(value: String) -> String {
 
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
+    let cli = cli::Cli::parse();
+
     // Enable tracing using Tokio's https://tokio.rs/#tk-lib-tracing
+    let env_filter = match &cli.log {
+        Some(filter) => tracing_subscriber::EnvFilter::new(filter),
+        None => tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| "clicker_data_collector_server=trace,clicker_data_collector=trace,tower_http=info".into()),
+    };
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "clicker_data_collector_server=trace,clicker_data_collector=trace,tower_http=info".into()),
-        )
+        .with(env_filter)
         .with(tracing_subscriber::fmt::layer().with_target(false))
         .init();
 
-    let emulate_freq = std::env::var("EMULATE_FREQ")
-        .map(|v| v.parse::<f32>().unwrap_or_default())
-        .ok();
-    if let Some(f) = &emulate_freq {
-        tracing::warn!("Emulating frequency: {}", f);
-    }
+    let capture = cli
+        .capture
+        .as_ref()
+        .and_then(|path| match clicker_data_collector::Capture::create(path) {
+            Ok(c) => {
+                tracing::warn!("Capturing clicker exchange to {:?}", path);
+                Some(c)
+            }
+            Err(e) => {
+                tracing::error!("Failed to create capture file {:?}: {}", path, e);
+                None
+            }
+        });
 
     tracing::info!("Loading config...");
-    let (config, config_file) = clicker_data_collector::Config::load();
+    let (mut config, config_file) = match &cli.config {
+        Some(path) => clicker_data_collector::Config::load_from(path.clone()),
+        None => clicker_data_collector::Config::load(),
+    };
     tracing::info!("Config loaded:\n{}", config);
 
-    //let mut clicker = clicker_data_collector::FakeClicker::new(std::time::Duration::from_secs(1));
-    let mut clicker = clicker_data_collector::Clicker::new(
-        config.rk_meter_port.clone(),
-        std::time::Duration::from_millis(250),
-    );
-    tracing::warn!("Testing connection...");
-    if let Err(e) = clicker.test().await {
-        panic!("Failed to connect to clicker: {:?}", e);
+    let catalog_file = cli
+        .catalog
+        .clone()
+        .unwrap_or_else(|| config_file.with_file_name("resonator_types.json"));
+    let resonator_catalog = match clicker_data_collector::ResonatorCatalog::load(&catalog_file) {
+        Ok(c) => {
+            tracing::info!(
+                "Resonator catalog {:?}: {} types",
+                catalog_file,
+                c.types().len()
+            );
+            c
+        }
+        Err(e) => {
+            tracing::error!("Failed to load resonator catalog {:?}: {}", catalog_file, e);
+            Default::default()
+        }
+    };
+
+    // Переопределения из командной строки в файл настроек не сохраняются
+    if let Some(web_port) = cli.web_port {
+        config.web_port = web_port;
+    }
+    if let Some(cycles) = cli.cycles {
+        config.cycles = cycles;
+    }
+    if let Some(path) = &cli.emulation {
+        config.emulation = clicker_data_collector::EmulationProfile::load(path)?;
     }
-    let clicker_ctrl = clicker_data_collector::ClickerController::new(
-        clicker,
-        std::time::Duration::from_millis(250), // интервал опроса
+    if let Some(freq) = cli.emulate_freq {
+        config.emulation.nominal_freq = freq;
+    }
+    let device = cli.device.clone().unwrap_or_else(|| {
+        if cli.emulate_freq.is_some() || cli.emulation.is_some() {
+            cli::Device::Fake
+        } else {
+            config
+                .rk_meter_port
+                .parse()
+                .unwrap_or_else(|_| cli::Device::Port(config.rk_meter_port.clone()))
+        }
+    });
+    match &device {
+        cli::Device::Port(port) => config.rk_meter_port = port.clone(),
+        cli::Device::Fake => config.rk_meter_port = device::FAKE_PORT.to_string(),
+        cli::Device::Replay(_) => {}
+    }
+
+    let mut clicker_ctrl = clicker_data_collector::ClickerController::new(
+        Duration::from_millis(cli.interval), // интервал опроса
         config.cycles, // цыклов переключения Rk -> Freq -> Rk для получения данных
     );
 
+    match device {
+        cli::Device::Fake => {
+            tracing::warn!("Emulating clicker");
+            device::attach_fake(&mut clicker_ctrl, config.emulation.clone());
+        }
+        cli::Device::Replay(path) => {
+            let speed = cli.replay_speed;
+            tracing::warn!("Replaying {:?} at speed {}", path, speed);
+            let path = path.to_string_lossy().to_string();
+            clicker_ctrl.attach(move || {
+                clicker_data_collector::ReplayClicker::connect(path.clone(), speed)
+            });
+        }
+        cli::Device::Port(port) => {
+            tracing::warn!("Testing connection...");
+            match clicker_data_collector::Clicker::connect(
+                port.clone(),
+                CLICKER_TIMEOUT,
+                config.retries,
+            )
+            .await
+            {
+                Ok(_) => {
+                    device::attach_clicker(&mut clicker_ctrl, port, config.retries, capture.clone())
+                }
+                Err(e) => tracing::error!(
+                    "Failed to connect to clicker on {}: {:?}, starting without device",
+                    port,
+                    e
+                ),
+            }
+        }
+    }
+
     // State for our application
     let mut minijinja = Environment::new();
     minijinja
@@ -92,18 +187,36 @@ async fn main() -> Result<(), std::io::Error> {
     minijinja.add_filter("float2dgt", float2dgt);
 
     let web_port = config.web_port;
+    let auto_measure = config.auto_measure;
 
     let data_model = DataModel::default();
 
     let app_state = AppState {
         engine: Engine::from(minijinja),
-        config,
+        config: Arc::new(Mutex::new(config)),
         config_file,
 
         data_model: Arc::new(Mutex::new(data_model)),
+        catalog: Arc::new(Mutex::new(resonator_catalog)),
+        catalog_file: catalog::CatalogFile(catalog_file),
         clicker_ctrl: Arc::new(Mutex::new(clicker_ctrl)),
+        measure_feed: clicker_data_collector::MeasureFeed::new(),
+        capture,
     };
 
+    events::spawn_event_log(app_state.clicker_ctrl.lock().await.subscribe_events());
+
+    if auto_measure {
+        auto_measure::start_auto(
+            app_state.config.clone(),
+            app_state.data_model.clone(),
+            app_state.catalog.clone(),
+            app_state.clicker_ctrl.clone(),
+            app_state.measure_feed.clone(),
+        )
+        .await;
+    }
+
     // Build our application with some routes
     let app = Router::new()
         .route("/", get(|| async { Redirect::permanent("/work") }))
@@ -117,6 +230,20 @@ async fn main() -> Result<(), std::io::Error> {
         .route("/report", get(handle_generate_report_excel))
         .route("/config", get(handle_config).patch(handle_config_and_save))
         //.route("/config-and-save", patch(handle_config_and_save))
+        .route(
+            "/device",
+            get(handle_device_get)
+                .post(handle_device_connect)
+                .delete(handle_device_disconnect),
+        )
+        .route("/device/test", post(handle_device_test))
+        .route("/ports", get(handle_ports_get))
+        .route("/ports/probe", get(handle_ports_probe))
+        .route("/auto", get(handle_auto_get).put(handle_auto_put))
+        .route("/live", get(handle_live))
+        .route("/events", get(handle_events))
+        .route("/catalog", get(handle_catalog_get).put(handle_catalog_put))
+        .route("/catalog/:name", delete(handle_catalog_delete))
         .route("/static/:path/:file", get(static_files::handle_static))
         .route("/lib/*path", get(static_files::handle_lib))
         // rest_api
@@ -126,6 +253,8 @@ async fn main() -> Result<(), std::io::Error> {
                 .post(handle_measurements_append)
                 .delete(handle_measurements_cancel),
         )
+        .route("/Measurements/state", get(handle_measure_state))
+        .route("/Measurements/progress", get(handle_measure_progress))
         .route(
             "/Measurements/:id",
             put(handle_measurements_put)
@@ -139,7 +268,7 @@ async fn main() -> Result<(), std::io::Error> {
     // In practice: Use graceful shutdown.
     // Note that Axum has great examples for a log of practical scenarios,
     // including graceful shutdown (https://github.com/tokio-rs/axum/tree/main/examples)
-    let addr = SocketAddr::from(([0, 0, 0, 0], web_port));
+    let addr = SocketAddr::new(cli.bind, web_port);
 
     tracing::info!("Listening on {}", addr);
     axum_server::bind(addr).serve(app.into_make_service()).await
@@ -3,12 +3,192 @@
 
 var notty: Noty | null = null;
 
+interface IDiagnostics {
+    requests: number,
+    responses: number,
+    stale_frames: number,
+    timeouts: number,
+    retries: number,
+    resync_events: number,
+    dropped_bytes: number,
+    decode_errors: number,
+}
+
+interface IDeviceStatus {
+    port: string,
+    state: string,
+    attempt?: number,
+    error?: string,
+    diagnostics: IDiagnostics,
+}
+
+function show_device_status(status: IDeviceStatus) {
+    let text = `${status.port}: ${status.state}`;
+    if (status.attempt !== undefined) {
+        text += ` (попытка ${status.attempt})`;
+    }
+    if (status.error !== undefined) {
+        text += ` - ${status.error}`;
+    }
+    $('#device-state').text(text);
+
+    const d = status.diagnostics;
+    $('#device-diagnostics').text(
+        `запросов: ${d.requests}, ответов: ${d.responses}, устаревших: ${d.stale_frames}, ` +
+        `без ответа: ${d.timeouts}, повторов: ${d.retries}, ` +
+        `потерь синхронизации: ${d.resync_events}, отброшено байт: ${d.dropped_bytes}, ` +
+        `ошибок декодирования: ${d.decode_errors}`);
+}
+
+interface IPortInfo {
+    name: string,
+    port_type: string,
+    vid?: number,
+    pid?: number,
+    serial_number?: string,
+    manufacturer?: string,
+    product?: string,
+    is_clicker?: boolean,
+    probe_error?: string,
+}
+
+function to_hex(v: number): string {
+    return v.toString(16).padStart(4, '0').toUpperCase();
+}
+
+function fill_ports(ports: Array<IPortInfo>) {
+    const list = $('#ports-list');
+    list.empty();
+    ports.forEach((port) => {
+        let label = port.port_type;
+        if (port.vid !== undefined && port.vid !== null) {
+            label += ` ${to_hex(port.vid)}:${to_hex(port.pid)}`;
+        }
+        if (port.product) {
+            label += ` ${port.product}`;
+        }
+        if (port.is_clicker === true) {
+            label = `Щелкунчик - ${label}`;
+        }
+        list.append($('<option>').attr('value', port.name).text(label));
+    });
+}
+
+function load_ports(probe: boolean = false) {
+    $.ajax({
+        url: probe ? '/ports/probe' : '/ports',
+        method: 'GET',
+        dataType: 'json',
+        success: (ports: Array<IPortInfo>) => {
+            fill_ports(ports);
+            if (probe) {
+                const found = ports.filter((p) => p.is_clicker === true).map((p) => p.name);
+                if (found.length > 0) {
+                    noty_success(`Устройство найдено на: ${found.join(', ')}`);
+                } else {
+                    noty_error('Устройство не найдено ни на одном порту');
+                }
+            }
+        },
+        error: (e) => {
+            noty_error(e.responseText || e.statusText);
+        }
+    });
+}
+
+interface IResonatorType {
+    Name: string,
+    NominalFreq: number,
+    FreqTolerance: { Ppm?: number, Hz?: number },
+    MaxRk: number,
+    MaxFreqIqr: number,
+    MaxRkIqr?: number,
+}
+
+function tolerance_text(t: IResonatorType): string {
+    return t.FreqTolerance.Ppm !== undefined ? `${t.FreqTolerance.Ppm} ppm` : `${t.FreqTolerance.Hz} Гц`;
+}
+
+function load_catalog() {
+    $.ajax({
+        url: '/catalog',
+        method: 'GET',
+        dataType: 'json',
+        success: (types: Array<IResonatorType>) => {
+            const table = $('#catalog');
+            table.empty();
+            types.forEach((t) => {
+                const remove = $('<button type="button" class="btn btn-xs btn-danger"><i class="fas fa-trash"></i></button>')
+                    .on('click', () => {
+                        $.ajax({
+                            url: `/catalog/${encodeURIComponent(t.Name)}`,
+                            method: 'DELETE',
+                            success: load_catalog,
+                            error: (e) => noty_error(e.responseText || e.statusText),
+                        });
+                    });
+                table.append($('<tr>').append(
+                    $('<td>').text(t.Name),
+                    $('<td>').text(t.NominalFreq),
+                    $('<td>').text(tolerance_text(t)),
+                    $('<td>').text(t.MaxRk),
+                    $('<td>').text(t.MaxFreqIqr),
+                    $('<td>').text(t.MaxRkIqr === undefined || t.MaxRkIqr === null ? '---' : t.MaxRkIqr),
+                    $('<td>').append(remove),
+                ));
+            });
+        },
+        error: (e) => noty_error(e.responseText || e.statusText),
+    });
+}
+
+function save_resonator_type() {
+    const row = $('#catalog-new');
+    const field = (name: string) => row.find(`[name="${name}"]`).val().toString().trim();
+
+    const tolerance = {};
+    tolerance[field('FreqToleranceUnit')] = parseFloat(field('FreqTolerance'));
+    const resonator_type: IResonatorType = {
+        Name: field('Name'),
+        NominalFreq: parseFloat(field('NominalFreq')),
+        FreqTolerance: tolerance,
+        MaxRk: parseFloat(field('MaxRk')),
+        MaxFreqIqr: parseFloat(field('MaxFreqIqr')),
+        MaxRkIqr: field('MaxRkIqr') === '' ? null : parseFloat(field('MaxRkIqr')),
+    };
+
+    $.ajax({
+        url: '/catalog',
+        method: 'PUT',
+        data: JSON.stringify(resonator_type),
+        contentType: 'application/json',
+        success: () => {
+            noty_success(`Тип "${resonator_type.Name}" сохранен`);
+            load_catalog();
+        },
+        error: (e) => noty_error(e.responseText || e.statusText),
+    });
+}
+
+function device_request(url: string, method: string) {
+    $.ajax({
+        url: url,
+        method: method,
+        dataType: 'json',
+        success: show_device_status,
+        error: (e) => {
+            noty_error(e.responseText || e.statusText);
+        }
+    });
+}
+
 // on page loaded jquery
 $(() => {
     $('.adj-input').on('input', (ev) => {
         const $target = $(ev.target);
         const parameter = $target.prop('name');
-        const value = parseFloat($target.val().toString());
+        const raw = $target.val().toString();
+        const value = (parameter == 'RkMeterPort' || parameter == 'MeasureStrategy' || parameter == 'QuantileMethod' || parameter == 'OutlierRejection') ? raw : parseFloat(raw);
 
         var data = {};
         data[parameter] = value;
@@ -23,4 +203,28 @@ $(() => {
             }
         });
     });
+
+    $('#device-connect').on('click', () => device_request('/device', 'POST'));
+    $('#device-disconnect').on('click', () => device_request('/device', 'DELETE'));
+    $('#device-test').on('click', () => {
+        $.ajax({
+            url: '/device/test',
+            method: 'POST',
+            success: (text: string) => {
+                noty_success(text);
+            },
+            error: (e) => {
+                noty_error(e.responseText || e.statusText);
+            }
+        });
+    });
+
+    $('#catalog-save').on('click', save_resonator_type);
+    load_catalog();
+
+    $('#ports-probe').on('click', () => load_ports(true));
+    load_ports();
+
+    device_request('/device', 'GET');
+    setInterval(() => device_request('/device', 'GET'), 2000);
 });
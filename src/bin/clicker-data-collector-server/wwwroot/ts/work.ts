@@ -13,31 +13,172 @@ interface IResonatorData {
     timestamp: string,
     F: number,
     F_deviation: number,
+    F_offset?: number,
+    F_offset_ppm?: number,
     Freqs: Array<number>,
+    F_raw: number,
+    F_raw_deviation: number,
+    RejectedFreqs: Array<number>,
     Rk: number,
     Rk_deviation: number,
     Rks: Array<number>,
+    Rk_raw: number,
+    Rk_raw_deviation: number,
+    RejectedRks: Array<number>,
+    DiscardedFreqs: Array<number>,
+    DiscardedRks: Array<number>,
+    Segments: Array<ISegment>,
+    Samples: Array<ITimedSample>,
+    F_repeatability?: IRepeatability,
+    Rk_repeatability?: IRepeatability,
+    Verdict?: IVerdict,
+    Attempts: number,
+    Unstable: boolean,
     comment: String,
 }
 
-interface IBoxPlot {
+interface IStatistics {
+    count: number,
+    min: number,
+    max: number,
+    mean: number,
+    std_dev: number,
+    std_err: number,
     median: number,
     q1: number,
     q3: number,
     iqr: number,
+    fence: number,
     lower_bound: number,
     upper_bound: number,
+    confidence: number,
+    ci_lower: number,
+    ci_upper: number,
+}
+
+interface ISegment {
+    mode: string,
+    values: Array<number>,
+    stat: IStatistics,
+    complete: boolean,
+}
+
+interface IFrequencyOffset {
+    hz: number,
+    ppm: number,
+}
+
+interface IMeasurements {
+    records: Array<IResonatorData>,
+    total: number,
+    mean_offset?: IFrequencyOffset,
+}
+
+interface IVerdict {
+    passed: boolean,
+    reasons: Array<string>,
+}
+
+interface IResonatorType {
+    Name: string,
+}
+
+interface ITimedSample {
+    mode: string,
+    value: number,
+    offset_ms: number,
+    unix_ms: number,
+    status: string,
+}
+
+interface IRepeatability {
+    cycles: number,
+    range: number,
+    std_dev: number,
 }
 
 interface IMeasureProcessStat {
     timestamp: number,
     state: string,
+    reason?: any,
 
     freqs: Array<number>,
     rks: Array<number>,
 
-    freqs_avg?: IBoxPlot,
-    rks_avg?: IBoxPlot,
+    discarded_freqs: Array<number>,
+    discarded_rks: Array<number>,
+
+    segments: Array<ISegment>,
+    samples: Array<ITimedSample>,
+
+    rejected_freqs: Array<number>,
+    rejected_rks: Array<number>,
+
+    freqs_avg?: IStatistics,
+    rks_avg?: IStatistics,
+
+    freqs_raw_avg?: IStatistics,
+    rks_raw_avg?: IStatistics,
+
+    freqs_repeatability?: IRepeatability,
+    rks_repeatability?: IRepeatability,
+
+    // Попытка измерения при повторе из-за большого разброса
+    attempt: number,
+    attempts: number,
+    retry: boolean,
+    unstable: boolean,
+}
+
+// Повторяемость от цикла к циклу: размах медиан фаз
+function repeatability_text(r: IRepeatability | undefined | null, mu: string): string {
+    if (r === undefined || r === null) {
+        return '---';
+    }
+    return `${round_to_2_digits(r.range)} ${mu} (циклов: ${r.cycles}, СКО: ${round_to_2_digits(r.std_dev)} ${mu})`;
+}
+
+// Заключение о годности для таблицы измерений
+function verdict_renderer(value: IVerdict | null, record: IResonatorData): string {
+    const unstable = record.Unstable
+        ? ' <span class="text-warning" title="Нестабилен: разброс превышает пороги во всех попытках"><i class="fas fa-exclamation-triangle"></i></span>'
+        : '';
+    if (value === undefined || value === null) {
+        return '<span class="text-muted" title="Тип резонатора не найден в каталоге">---</span>' + unstable;
+    } else if (value.passed) {
+        return '<span class="text-success"><i class="fas fa-check"></i></span>' + unstable;
+    } else {
+        const reason = value.reasons.join('; ').replace(/"/g, '&quot;');
+        return `<span class="text-danger" title="${reason}"><i class="fas fa-times"></i></span>` + unstable;
+    }
+}
+
+function verdict_text(value: IVerdict | undefined | null): string {
+    if (value === undefined || value === null) {
+        return 'тип резонатора не найден в каталоге';
+    } else if (value.passed) {
+        return 'годен';
+    } else {
+        return `не годен: ${value.reasons.join('; ')}`;
+    }
+}
+
+// Среднее отклонение частоты партии от номинала под таблицей измерений
+function show_mean_offset(offset: IFrequencyOffset | undefined | null) {
+    if (offset === undefined || offset === null) {
+        $('#mean-offset').text('Номинал партии не задан');
+    } else {
+        $('#mean-offset').text(
+            `Среднее отклонение от номинала: ${round_to_2_digits(offset.hz)} Гц (${round_to_2_digits(offset.ppm)} ppm)`);
+    }
+}
+
+// Значения series: учтенные и отброшенные как выбросы с индексами rejected
+function split_rejected(series: Array<number>, rejected: Array<number>): [Array<number>, Array<number>] {
+    return [
+        series.filter((_v, i) => !rejected.includes(i)),
+        series.filter((_v, i) => rejected.includes(i)),
+    ];
 }
 
 // ---------------------------------------------------------------------------------------------
@@ -46,6 +187,7 @@ const schema = joi.object({
     data_type: joi.string().min(1).required(),
     route_id: joi.string().min(1).required(),
     ambient_temperature_range: joi.string().min(1).required(),
+    nominal_freq: joi.number().positive().allow(''),
     date: joi.date().required(),
 });
 
@@ -69,7 +211,13 @@ $(() => {
     // 5. https://gijgo.com/grid/demos/nested-jquery-grids : Details template and render it
     grid = $('#grid').grid({
         uiLibrary: 'bootstrap4',
-        dataSource: '/Measurements',
+        dataSource: {
+            url: '/Measurements',
+            success: (response: IMeasurements) => {
+                show_mean_offset(response.mean_offset);
+                grid.render(response);
+            },
+        },
         primaryKey: 'id',
         iconsLibrary: 'fontawesome',
         detailTemplate: '<div></dev>',
@@ -80,6 +228,8 @@ $(() => {
             { field: 'id', title: '№', width: 45, type: 'number', priority: 1 },
             { field: 'F', title: 'F, Гц', width: 90, decimalDigits: 2, priority: 2 },
             { field: 'Rk', title: 'Rk, кОм', width: 90, decimalDigits: 1, priority: 2 },
+            { field: 'F_offset_ppm', title: 'ΔF, ppm', width: 80, decimalDigits: 1, priority: 2 },
+            { field: 'Verdict', title: 'Годен', width: 70, align: 'center', renderer: verdict_renderer, priority: 1 },
             { field: 'Comment', title: 'Комментарий', editor: true, type: 'text', priority: 0 },
             { field: 'timestamp', title: 'Снято в', hidden: true, type: 'date', format: 'HH:MM:ss' },
             //{ field: 'F_deviation', title: 'ΔF, Гц', hidden: true, type: 'number', priority: 0, decimalDigits: 2 },
@@ -177,17 +327,27 @@ $(() => {
     }).on('detailExpand', (_e, $detailWrapper: JQuery<HTMLDivElement>, id: string) => {
         const record: IResonatorData = grid.getById(id);
         const width = $detailWrapper.width();
+        const [freqs, rejected_freqs] = split_rejected(record.Freqs, record.RejectedFreqs);
+        const [rks, rejected_rks] = split_rejected(record.Rks, record.RejectedRks);
 
         const f_canvas: JQuery<HTMLCanvasElement> = $(`<canvas height="50" width="${width}"/>`);
         const rk_canvas: JQuery<HTMLCanvasElement> = $(`<canvas height="50" width="${width}"/>`);
 
         $detailWrapper
             .html(`<b>Снято в</b>: ${record.timestamp}`)
+            .append(`, <b>заключение</b>: ${verdict_text(record.Verdict)}`)
+            .append(`, <b>попыток</b>: ${record.Attempts}${record.Unstable ? ' (нестабилен)' : ''}`)
             .append('<hr/>')
             .append(`<b>Разброс частоты</b>: ${round_to_2_digits(record.F_deviation)} Гц`)
+            .append(`, <b>повторяемость</b>: ${repeatability_text(record.F_repeatability, 'Гц')}`)
+            .append(`<br/><b>По всем отсчетам</b>: ${round_to_2_digits(record.F_raw)} ±${round_to_2_digits(record.F_raw_deviation)} Гц`)
+            .append(`, <b>выбросов</b>: ${rejected_freqs.length}`)
             .append(f_canvas)
             .append('<hr/>')
             .append(`<b>Разброс Rk</b>: ${round_to_2_digits(record.Rk_deviation)} кОм`)
+            .append(`, <b>повторяемость</b>: ${repeatability_text(record.Rk_repeatability, 'кОм')}`)
+            .append(`<br/><b>По всем отсчетам</b>: ${round_to_2_digits(record.Rk_raw)} ±${round_to_2_digits(record.Rk_raw_deviation)} кОм`)
+            .append(`, <b>выбросов</b>: ${rejected_rks.length}`)
             .append(rk_canvas);
 
         new Chart(f_canvas, {
@@ -198,9 +358,25 @@ $(() => {
                     backgroundColor: 'rgba(0, 191, 255,0.5)',
                     borderColor: 'rgb(0, 128, 255)',
                     borderWidth: 1,
-                    data: [record.Freqs],
+                    data: [freqs],
                     itemRadius: 3,
                     itemBorderColor: 'black',
+                }, {
+                    label: 'F выбросы',
+                    backgroundColor: 'rgba(255, 165, 0, 0.3)',
+                    borderColor: 'rgb(255, 140, 0)',
+                    borderWidth: 1,
+                    data: [rejected_freqs],
+                    itemRadius: 3,
+                    itemBorderColor: 'orange',
+                }, {
+                    label: 'F отброшено',
+                    backgroundColor: 'rgba(128, 128, 128, 0.3)',
+                    borderColor: 'rgb(128, 128, 128)',
+                    borderWidth: 1,
+                    data: [record.DiscardedFreqs],
+                    itemRadius: 3,
+                    itemBorderColor: 'grey',
                 }]
             },
             options: {
@@ -222,9 +398,25 @@ $(() => {
                     backgroundColor: 'rgba(255, 0, 64, 0.5)',
                     borderColor: 'rgb(255, 0, 0)',
                     borderWidth: 1,
-                    data: [record.Rks],
+                    data: [rks],
                     itemRadius: 3,
                     itemBorderColor: 'black',
+                }, {
+                    label: 'Rk выбросы',
+                    backgroundColor: 'rgba(255, 165, 0, 0.3)',
+                    borderColor: 'rgb(255, 140, 0)',
+                    borderWidth: 1,
+                    data: [rejected_rks],
+                    itemRadius: 3,
+                    itemBorderColor: 'orange',
+                }, {
+                    label: 'Rk отброшено',
+                    backgroundColor: 'rgba(128, 128, 128, 0.3)',
+                    borderColor: 'rgb(128, 128, 128)',
+                    borderWidth: 1,
+                    data: [record.DiscardedRks],
+                    itemRadius: 3,
+                    itemBorderColor: 'grey',
                 }]
             },
             options: {
@@ -291,6 +483,7 @@ $(() => {
         const data_type = $(form).find("#data_type");
         const route_id = $(form).find("#route_id");
         const ambient_temperature_range = $(form).find("#ambient_temperature_range");
+        const nominal_freq = $(form).find("#nominal_freq");
         const comment = $(form).find("#comment");
         const date = $(form).find("#date");
 
@@ -298,6 +491,7 @@ $(() => {
             data_type: data_type.val(),
             route_id: route_id.val(),
             ambient_temperature_range: ambient_temperature_range.val(),
+            nominal_freq: nominal_freq.val(),
             date: date.val(),
         });
 
@@ -305,6 +499,7 @@ $(() => {
             data_type: null,
             route_id: null,
             ambient_temperature_range: null,
+            nominal_freq: null,
             date: null,
         };
 
@@ -340,12 +535,15 @@ $(() => {
                     data_type: data_type.val(),
                     route_id: route_id.val(),
                     ambient_temperature_range: ambient_temperature_range.val(),
+                    nominal_freq: nominal_freq.val(),
                     date: date.val(),
                     comment: comment.val(),
                 }),
                 success: (_data) => {
                     noty_success('Отчет успешно создан.');
                     dialog.close();
+                    // годность зависит от типа партии
+                    grid && grid.reload();
                 },
                 error: (err) => {
                     noty_error(err.responseText || err.statusText);
@@ -368,9 +566,119 @@ $(() => {
     $('#btnCancel').on('click', function () {
         dialog.close();
     });
+
+    $('#auto-measure').on('click', (ev) => {
+        ev.preventDefault();
+        toggle_auto_measure();
+    });
+    load_auto_state();
+    setInterval(load_auto_state, 1000);
+
+    attach_measure();
+    start_live_display();
 });
 
+interface ILiveReading {
+    mode: string,
+    value: number | null,
+    unix_ms: number,
+}
+
+// Отсчетов в секунду для индикатора текущих показаний
+const LIVE_RATE = 5;
+
+// Индикатор текущих показаний устройства, устаревшие показания показываются серым
+function start_live_display() {
+    const display = $('#live-display');
+    const mode = $('#live-mode');
+    let stale_timer: any = undefined;
+
+    const source = new EventSource(`/live?rate=${LIVE_RATE}`);
+    source.onmessage = (ev: MessageEvent) => {
+        const reading: ILiveReading = JSON.parse(ev.data);
+        const is_freq = reading.mode == 'Freq';
+
+        mode.text(is_freq ? 'Частота, Гц' : 'Сопротивление, кОм');
+        display
+            .text(reading.value === null ? '---' : round_to_2_digits(reading.value))
+            .toggleClass('text-info', is_freq)
+            .toggleClass('text-danger', !is_freq)
+            .removeClass('text-muted');
+
+        clearTimeout(stale_timer);
+        stale_timer = setTimeout(() => display.addClass('text-muted'), 2000);
+    };
+}
+
+interface IAutoMeasureState {
+    state: string,
+}
+
+const AUTO_STATE_TEXT = {
+    Disabled: 'Авто: выкл.',
+    WaitingInsertion: 'Авто: установите резонатор',
+    Measuring: 'Авто: измерение',
+    WaitingRemoval: 'Авто: снимите резонатор',
+};
+
+var auto_state: string = 'Disabled';
+
+const TIMEOUT_TEXT = {
+    FirstSwitch: 'устройство не переключает режим, проверьте установку резонатора',
+    Phase: 'режим устройства не переключается слишком долго',
+    Total: 'превышено максимальное время измерения',
+};
+
+function show_auto_state(state: IAutoMeasureState) {
+    // измерение завершено - в таблице новый резонатор
+    if (auto_state == 'Measuring' && state.state != 'Measuring') {
+        grid.reload();
+    }
+    if (auto_state != 'Measuring' && state.state == 'Measuring') {
+        attach_measure();
+    }
+    auto_state = state.state;
+
+    $('#auto-state').text(AUTO_STATE_TEXT[state.state] || state.state);
+    $('#nav-bar-auto').toggleClass('active', state.state != 'Disabled');
+}
+
+function load_auto_state() {
+    $.ajax({
+        url: '/auto',
+        method: 'GET',
+        dataType: 'json',
+        success: show_auto_state,
+    });
+}
+
+function toggle_auto_measure() {
+    $.ajax({
+        url: '/auto',
+        method: 'PUT',
+        data: (auto_state == 'Disabled').toString(),
+        dataType: 'json',
+        success: show_auto_state,
+        error: (e) => noty_error(e.responseText || e.statusText),
+    });
+}
+
+function load_resonator_types() {
+    $.ajax({
+        url: '/catalog',
+        method: 'GET',
+        dataType: 'json',
+        success: (types: Array<IResonatorType>) => {
+            const list = $('#types-list');
+            list.empty();
+            types.forEach((t) => list.append($('<option>').attr('value', t.Name)));
+        }
+    });
+}
+
 function reload_global() {
+    load_resonator_types();
+
     $.ajax({
         url: '/global',
         method: 'GET',
@@ -379,6 +687,7 @@ function reload_global() {
             $('#data_type').val(data.data_type);
             $('#route_id').val(data.route_id);
             $('#ambient_temperature_range').val(data.ambient_temperature_range);
+            $('#nominal_freq').val(data.nominal_freq);
             $('#comment').val(data.comment);
             $('#date').val(data.date);
         }
@@ -423,6 +732,32 @@ function add_res(id?: number, insertBefore: boolean = false) {
         config.body = insertBefore.toString();
     }
 
+    follow_measure(config);
+}
+
+// Подключиться к измерению, запущенному другим клиентом или автоматически
+function attach_measure() {
+    $.ajax({
+        url: '/Measurements/state',
+        method: 'GET',
+        dataType: 'json',
+        success: (state: { running: boolean }) => {
+            if (state.running) {
+                follow_measure({ url: '/Measurements/progress', method: 'GET' });
+            }
+        }
+    });
+}
+
+// Измерение, ход которого показывается в диалоге
+var following_measure = false;
+
+function follow_measure(config: oboe.Options) {
+    if (following_measure) {
+        return;
+    }
+    following_measure = true;
+
     const empty_text = '---';
     
     const freq_disp = $('#current-freq-display');
@@ -437,27 +772,73 @@ function add_res(id?: number, insertBefore: boolean = false) {
     freq_iqr_display.text(empty_text);
     rk_iqr_display.text(empty_text)
 
+    // Отброшенный отсчет переходного процесса показывается серым
+    let discarded_freqs = 0;
+    let discarded_rks = 0;
+    const show_value = (disp: JQuery<HTMLElement>, value: number, discarded: boolean) => {
+        disp.text(round_to_2_digits(value)).toggleClass('text-muted', discarded);
+    };
+
     MPdailog.open('Измерение');
     oboe(config)
         .done((data: IMeasureProcessStat) => {
             if (data.state == "Finished") {
-                noty_success("Измерение завершено");
+                if (data.retry) {
+                    noty({
+                        type: 'warning',
+                        text: `Разброс превышает пороги, повтор измерения ${data.attempt + 1}/${data.attempts}`,
+                        timeout: 3000
+                    });
+                    discarded_freqs = 0;
+                    discarded_rks = 0;
+                    return;
+                }
+                following_measure = false;
+                if (data.unstable) {
+                    noty({
+                        type: 'warning',
+                        text: '<i class="fas fa-exclamation-triangle"></i> Резонатор нестабилен: разброс превышает пороги во всех попытках',
+                        timeout: 5000
+                    });
+                } else {
+                    noty_success("Измерение завершено");
+                }
                 MPdailog.close();
                 grid.reload();
             }
             if (data.state == "Interrupted") {
-                noty({
-                    type: 'warning',
-                    text: '<i class="fas fa-heart-broken"></i> Измерение отменено',
-                    timeout: 3000
-                });
+                if (data.reason?.DeviceError !== undefined) {
+                    noty_error(`Измерение прервано: ${data.reason.DeviceError}`);
+                } else if (data.reason?.Timeout !== undefined) {
+                    noty_error(`Измерение прервано: ${TIMEOUT_TEXT[data.reason.Timeout] || data.reason.Timeout}`);
+                } else if (data.reason == "ChannelClosed") {
+                    noty_error('Измерение прервано: нет данных от устройства');
+                } else {
+                    noty({
+                        type: 'warning',
+                        text: '<i class="fas fa-heart-broken"></i> Измерение отменено',
+                        timeout: 3000
+                    });
+                }
+                following_measure = false;
                 MPdailog.close();
+                grid.reload();
             }
 
             if (data.state == "Running") {
-                
+                if (data.discarded_freqs.length > discarded_freqs) {
+                    discarded_freqs = data.discarded_freqs.length;
+                    show_value(freq_disp, data.discarded_freqs.pop(), true);
+                    return;
+                }
+                if (data.discarded_rks.length > discarded_rks) {
+                    discarded_rks = data.discarded_rks.length;
+                    show_value(rk_disp, data.discarded_rks.pop(), true);
+                    return;
+                }
+
                 if (data.freqs.length > 0) {
-                    freq_disp.text(round_to_2_digits(data.freqs.pop()))
+                    show_value(freq_disp, data.freqs.pop(), false);
                     freq_iqr_display
                         .text(`${round_to_2_digits(data.freqs_avg.median)} ±${round_to_2_digits(data.freqs_avg.iqr)}`);
                 } else {
@@ -466,7 +847,7 @@ function add_res(id?: number, insertBefore: boolean = false) {
 
                 
                 if (data.rks.length > 0) {
-                    rk_disp.text(round_to_2_digits(data.rks.pop()));
+                    show_value(rk_disp, data.rks.pop(), false);
                     rk_iqr_display
                         .text(`${round_to_2_digits(data.rks_avg.median)} ±${round_to_2_digits(data.rks_avg.iqr)}`);
                 } else {
@@ -474,6 +855,7 @@ function add_res(id?: number, insertBefore: boolean = false) {
                 }
             }
         }).fail((err: oboe.FailReason) => {
+            following_measure = false;
             noty_error(err.body || err.statusCode.toString());
             MPdailog.close();
         });
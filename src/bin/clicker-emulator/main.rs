@@ -0,0 +1,120 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::Parser;
+use clicker_data_collector::{DeviceEmulator, EmulationProfile};
+use tracing_subscriber::prelude::*;
+
+/// Эмулятор устройства "Щелкунчик": отвечает на запросы по протоколу реального устройства
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// Адрес для входящих TCP подключений (в RkMeterPort указать tcp://<адрес>)
+    #[arg(short, long, default_value = "127.0.0.1:4000")]
+    listen: SocketAddr,
+
+    /// Создать псевдотерминал вместо TCP сервера (только unix)
+    #[arg(long)]
+    pty: bool,
+
+    /// Профиль эмуляции (JSON, формат раздела Emulation файла настроек)
+    #[arg(long)]
+    profile: Option<PathBuf>,
+
+    /// Период переключения режимов Rk/F, мс, заменяет значение из профиля
+    #[arg(short, long)]
+    switch_period: Option<u64>,
+
+    /// Вероятность ответа со статусом ошибки, 0.0..1.0, заменяет значение из профиля
+    #[arg(short, long)]
+    error_probability: Option<f64>,
+
+    #[arg(skip)]
+    emulation: EmulationProfile,
+}
+
+impl Cli {
+    fn emulator(&self) -> DeviceEmulator {
+        DeviceEmulator::new(self.emulation.clone())
+    }
+
+    /// Собрать профиль эмуляции из файла и ключей командной строки
+    fn load_profile(&mut self) -> std::io::Result<()> {
+        if let Some(path) = &self.profile {
+            self.emulation = EmulationProfile::load(path)?;
+        }
+        if let Some(switch_period) = self.switch_period {
+            self.emulation.switch_period_ms = switch_period.max(1);
+        }
+        if let Some(error_probability) = self.error_probability {
+            self.emulation.error_probability = error_probability;
+        }
+        tracing::info!("Emulation profile: {}", self.emulation);
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+async fn serve_pty(cli: Cli) -> std::io::Result<()> {
+    use tokio_serial::SerialPort;
+
+    let (master, slave) = tokio_serial::SerialStream::pair()?;
+    tracing::info!(
+        "Emulator pty: {}",
+        slave.name().unwrap_or_else(|| "<unknown>".to_string())
+    );
+
+    // slave должен оставаться открытым, пока работает эмулятор
+    let _slave = slave;
+    if let Err(e) = cli.emulator().serve(master).await {
+        tracing::error!("Emulator stopped: {:?}", e);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn serve_pty(_cli: Cli) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "pty is supported on unix only",
+    ))
+}
+
+async fn serve_tcp(cli: Cli) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(cli.listen).await?;
+    tracing::info!("Emulator listening on tcp://{}", listener.local_addr()?);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        stream.set_nodelay(true)?;
+        tracing::info!("Client connected: {}", peer);
+
+        let mut emulator = cli.emulator();
+        tokio::spawn(async move {
+            match emulator.serve(stream).await {
+                Ok(_) => tracing::info!("Client disconnected: {}", peer),
+                Err(e) => tracing::error!("Client {} error: {:?}", peer, e),
+            }
+        });
+    }
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "clicker_emulator=info,clicker_data_collector=debug".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_target(false))
+        .init();
+
+    let mut cli = Cli::parse();
+    cli.load_profile()?;
+
+    if cli.pty {
+        serve_pty(cli).await
+    } else {
+        serve_tcp(cli).await
+    }
+}
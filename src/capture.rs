@@ -0,0 +1,96 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, LineWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+/// Направление кадра
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum Direction {
+    /// Запрос к устройству
+    Tx,
+    /// Ответ устройства
+    Rx,
+}
+
+/// Запись файла захвата, одна строка JSON на кадр
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CaptureRecord {
+    /// Время записи кадра (RFC 3339)
+    pub timestamp: String,
+    /// Время от начала захвата, мкс
+    pub offset_us: u64,
+    pub direction: Direction,
+    /// Кадр protobuf без Magick и длины, hex
+    pub frame: String,
+}
+
+struct CaptureFile {
+    start: Instant,
+    writer: LineWriter<File>,
+}
+
+/// Запись всех кадров обмена с устройством в файл.
+/// Копии разделяют один файл, поэтому захват продолжается после переподключения
+#[derive(Clone)]
+pub struct Capture {
+    inner: Arc<Mutex<CaptureFile>>,
+}
+
+impl Capture {
+    pub fn create<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(CaptureFile {
+                start: Instant::now(),
+                writer: LineWriter::new(file),
+            })),
+        })
+    }
+
+    /// Записать кадр
+    pub fn record<M: prost::Message>(&self, direction: Direction, msg: &M) {
+        let mut inner = self.inner.lock().unwrap();
+        let record = CaptureRecord {
+            timestamp: Local::now().to_rfc3339(),
+            offset_us: inner.start.elapsed().as_micros() as u64,
+            direction,
+            frame: to_hex(&msg.encode_to_vec()),
+        };
+
+        let res = serde_json::to_writer(&mut inner.writer, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|_| inner.writer.write_all(b"\n"));
+        if let Err(e) = res {
+            tracing::error!("Failed to write capture: {}", e);
+        }
+    }
+}
+
+/// Прочитать файл захвата
+pub fn read_capture<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<CaptureRecord>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|l| !matches!(l, Ok(l) if l.trim().is_empty()))
+        .map(|l| serde_json::from_str(&l?).map_err(std::io::Error::from))
+        .collect()
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data_model::ResonatorData;
+
+/// Допуск частоты относительно номинала
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum FreqTolerance {
+    /// Миллионные доли номинала
+    Ppm(f32),
+    /// Гц
+    Hz(f32),
+}
+
+impl FreqTolerance {
+    /// Допуск в Гц для номинала `nominal`
+    pub fn hz(&self, nominal: f32) -> f32 {
+        match *self {
+            Self::Ppm(ppm) => nominal * ppm / 1e6,
+            Self::Hz(hz) => hz,
+        }
+    }
+}
+
+impl std::fmt::Display for FreqTolerance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ppm(ppm) => write!(f, "{ppm} ppm"),
+            Self::Hz(hz) => write!(f, "{hz} Гц"),
+        }
+    }
+}
+
+/// Тип резонатора: номинальные значения и пределы годности
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ResonatorType {
+    /// Название, совпадает с полем "Испытания" партии
+    pub name: String,
+    /// Номинальная частота, Гц
+    pub nominal_freq: f32,
+    /// Допустимое отклонение частоты от номинала
+    pub freq_tolerance: FreqTolerance,
+    /// Максимальное динамическое сопротивление
+    pub max_rk: f32,
+    /// Максимальный межквартильный размах частоты за измерение, Гц
+    pub max_freq_iqr: f32,
+    /// Максимальный межквартильный размах Rk за измерение, не проверяется если не задан
+    #[serde(default)]
+    pub max_rk_iqr: Option<f32>,
+}
+
+impl ResonatorType {
+    /// Проверить, что пределы заданы корректно
+    pub fn validate(&self) -> Result<(), String> {
+        let tolerance = match self.freq_tolerance {
+            FreqTolerance::Ppm(v) | FreqTolerance::Hz(v) => v,
+        };
+
+        if self.name.trim().is_empty() {
+            Err("Name Не может быть пустым".to_string())
+        } else if self.nominal_freq.is_nan() || self.nominal_freq <= 0.0 {
+            Err("NominalFreq Должна быть больше 0".to_string())
+        } else if tolerance.is_nan() || tolerance <= 0.0 {
+            Err("FreqTolerance Должен быть больше 0".to_string())
+        } else if self.max_rk.is_nan() || self.max_rk <= 0.0 {
+            Err("MaxRk Должно быть больше 0".to_string())
+        } else if self.max_freq_iqr.is_nan() || self.max_freq_iqr <= 0.0 {
+            Err("MaxFreqIqr Должен быть больше 0".to_string())
+        } else if self.max_rk_iqr.is_some_and(|v| v.is_nan() || v <= 0.0) {
+            Err("MaxRkIqr Должен быть больше 0".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Оценить годность резонатора `data`
+    pub fn evaluate(&self, data: &ResonatorData) -> Verdict {
+        let mut reasons = Vec::new();
+
+        let tolerance = self.freq_tolerance.hz(self.nominal_freq);
+        if exceeds((data.frequency - self.nominal_freq).abs(), tolerance) {
+            reasons.push(format!(
+                "F {:.2} Гц вне {} ±{}",
+                data.frequency, self.nominal_freq, self.freq_tolerance
+            ));
+        }
+        if exceeds(data.rk, self.max_rk) {
+            reasons.push(format!("Rk {:.2} больше {}", data.rk, self.max_rk));
+        }
+        if exceeds(data.frequency_deviation, self.max_freq_iqr) {
+            reasons.push(format!(
+                "Разброс F {:.3} Гц больше {} Гц",
+                data.frequency_deviation, self.max_freq_iqr
+            ));
+        }
+        if let Some(max_rk_iqr) = self.max_rk_iqr {
+            if exceeds(data.rk_deviation, max_rk_iqr) {
+                reasons.push(format!(
+                    "Разброс Rk {:.3} больше {}",
+                    data.rk_deviation, max_rk_iqr
+                ));
+            }
+        }
+
+        Verdict {
+            passed: reasons.is_empty(),
+            reasons,
+        }
+    }
+}
+
+/// `value` больше `limit` или одно из них не определено (NaN)
+fn exceeds(value: f32, limit: f32) -> bool {
+    value
+        .partial_cmp(&limit)
+        .is_none_or(|o| o == std::cmp::Ordering::Greater)
+}
+
+/// Заключение о годности резонатора
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Verdict {
+    pub passed: bool,
+    /// Причины негодности
+    pub reasons: Vec<String>,
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.passed {
+            write!(f, "Годен")
+        } else {
+            write!(f, "Не годен: {}", self.reasons.join("; "))
+        }
+    }
+}
+
+/// Каталог типов резонаторов, хранится в файле JSON
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct ResonatorCatalog {
+    types: Vec<ResonatorType>,
+}
+
+impl ResonatorCatalog {
+    /// Загрузить каталог из файла `path`, если файла нет - пустой каталог
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let f = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(f, self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn types(&self) -> &[ResonatorType] {
+        &self.types
+    }
+
+    /// Тип резонатора по названию
+    pub fn get(&self, name: &str) -> Option<&ResonatorType> {
+        self.types.iter().find(|t| t.name == name)
+    }
+
+    /// Добавить тип резонатора, тип с тем же названием заменяется
+    pub fn insert(&mut self, resonator_type: ResonatorType) {
+        match self
+            .types
+            .iter_mut()
+            .find(|t| t.name == resonator_type.name)
+        {
+            Some(t) => *t = resonator_type,
+            None => self.types.push(resonator_type),
+        }
+    }
+
+    /// Удалить тип резонатора по названию
+    pub fn remove(&mut self, name: &str) -> Option<ResonatorType> {
+        let index = self.types.iter().position(|t| t.name == name)?;
+        Some(self.types.remove(index))
+    }
+}
@@ -1,84 +1,173 @@
 use std::time::Duration;
 
 use futures::{SinkExt, StreamExt};
-use tokio_serial::SerialPortBuilderExt;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::Decoder;
 
 use crate::{
-    clicker_interface::{ClickerInterface, MeasureResult},
+    capture::{Capture, Direction},
+    clicker_interface::{ClickerInterface, Diagnostics, MeasureResult},
     protobuf::{
         self,
-        messages::{Mode, OutputReq, Status},
+        messages::{Mode, OutputReq, Response, Status},
         protobuf_md_codec::ProtobufMDCodec,
         Error,
     },
+    transport::Transport,
 };
 
-pub struct Clicker {
-    io: tokio_util::codec::Framed<tokio_serial::SerialStream, ProtobufMDCodec>,
+/// Устройство "Щелкунчик", подключенное через канал связи `IO`
+pub struct Clicker<IO = Transport> {
+    io: tokio_util::codec::Framed<IO, ProtobufMDCodec>,
     timeout: Duration,
+    retries: u32,
+    diagnostics: Diagnostics,
+    capture: Option<Capture>,
 }
 
-impl ClickerInterface<Error> for Clicker {
+/// Преобразовать ответ устройства в результат измерения
+pub(crate) fn response_to_result(resp: Response) -> Result<MeasureResult, Error> {
+    response_status(&resp)?;
+
+    let output = resp.output.ok_or(Error::Protocol(Status::ProtocolError))?;
+    let mode = output
+        .current_mode
+        .ok_or(Error::Protocol(Status::ProtocolError))?;
+    match Mode::try_from(mode) {
+        Ok(Mode::Rk) => Ok(MeasureResult::Rk(output.rk())),
+        Ok(Mode::F) => Ok(MeasureResult::Freq(output.freq())),
+        _ => Err(Error::Protocol(Status::ProtocolError)),
+    }
+}
+
+/// Проверить статус ответа устройства, неизвестный статус - ошибка протокола
+fn response_status(resp: &Response) -> Result<(), Error> {
+    match Status::try_from(resp.global_status) {
+        Ok(Status::Ok) => Ok(()),
+        Ok(e) => Err(Error::Protocol(e)),
+        Err(_) => Err(Error::Protocol(Status::ProtocolError)),
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin + Send> ClickerInterface<Error> for Clicker<IO> {
     async fn read(&mut self) -> Result<MeasureResult, Error> {
-        self.send_result_request().await?;
-        let resp = self.read_responce().await?;
-
-        match Status::try_from(resp.global_status).unwrap() {
-            Status::Ok => {
-                let output = resp.output.unwrap();
-                let mode = Mode::try_from(output.current_mode.unwrap());
-                match mode {
-                    Ok(Mode::Rk) => Ok(MeasureResult::Rk(output.rk())),
-                    Ok(Mode::F) => Ok(MeasureResult::Freq(output.freq())),
-                    _ => Err(Error::Protocol(Status::ProtocolError)),
-                }
-            }
-            e => Err(Error::Protocol(e)),
+        let resp = self
+            .request(|| {
+                let mut req = protobuf::new_request();
+                req.get_output_values = Some(OutputReq {
+                    get_main_values: Some(protobuf::messages::Empty {}),
+                    ..Default::default()
+                });
+                req
+            })
+            .await?;
+
+        response_to_result(resp)
+    }
+
+    fn diagnostics(&self) -> Diagnostics {
+        let codec = self.io.codec().stats();
+        Diagnostics {
+            resync_events: codec.resync_events,
+            dropped_bytes: codec.dropped_bytes,
+            decode_errors: codec.decode_errors,
+            ..self.diagnostics
         }
     }
 }
 
 impl Clicker {
-    pub fn new<'a>(port: impl Into<std::borrow::Cow<'a, str>>, timeout: Duration) -> Self {
-        let port = tokio_serial::new(port, 1500000)
-            .open_native_async()
-            .unwrap();
+    /// Открыть порт `port`: имя последовательного порта или `tcp://host:port`
+    /// * `timeout` - время ожидания ответа на запрос
+    /// * `retries` - сколько раз повторить запрос, если ответ не получен
+    pub async fn new(port: &str, timeout: Duration, retries: u32) -> Result<Self, Error> {
+        let io = Transport::open(port).await?;
+        Ok(Self::from_io(io, timeout, retries))
+    }
+
+    /// Открыть порт и проверить, что на нем отвечает устройство
+    pub async fn connect(port: String, timeout: Duration, retries: u32) -> Result<Self, Error> {
+        let mut clicker = Self::new(&port, timeout, retries).await?;
+        clicker.test().await?;
+        Ok(clicker)
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin + Send> Clicker<IO> {
+    /// Работать с устройством через произвольный канал связи
+    pub fn from_io(io: IO, timeout: Duration, retries: u32) -> Self {
         Self {
-            io: ProtobufMDCodec.framed(port),
+            io: ProtobufMDCodec::default().framed(io),
             timeout,
+            retries,
+            diagnostics: Diagnostics::default(),
+            capture: None,
         }
     }
 
-    pub async fn test(&mut self) -> Result<(), Error> {
-        let req = protobuf::new_request();
-
-        self.io.send(req).await?;
+    /// Записывать все кадры обмена в `capture`
+    pub fn with_capture(mut self, capture: Option<Capture>) -> Self {
+        self.capture = capture;
+        self
+    }
 
-        let resp = self.read_responce().await?;
-        match Status::try_from(resp.global_status).unwrap() {
-            Status::Ok => Ok(()),
-            e => Err(Error::Protocol(e)),
-        }
+    pub async fn test(&mut self) -> Result<(), Error> {
+        let resp = self.request(protobuf::new_request).await?;
+        response_status(&resp)
     }
 
-    async fn send_result_request(&mut self) -> Result<(), Error> {
-        let mut req = protobuf::new_request();
-        req.get_output_values = Some(OutputReq {
-            get_main_values: Some(protobuf::messages::Empty {}),
-            ..Default::default()
-        });
+    /// Отправить запрос, построенный `new_request`, и дождаться ответа на него.
+    /// Если ответ не получен, запрос повторяется с новым id до `retries` раз
+    async fn request(
+        &mut self,
+        new_request: impl Fn() -> protobuf::messages::Request,
+    ) -> Result<protobuf::messages::Response, Error> {
+        let mut attempt = 0;
+        loop {
+            let req = new_request();
+            let id = req.id;
+
+            self.diagnostics.requests += 1;
+            if let Some(capture) = &self.capture {
+                capture.record(Direction::Tx, &req);
+            }
+            self.io.send(req).await?;
 
-        self.io.send(req).await?;
-        Ok(())
+            match self.read_responce(id).await {
+                Err(Error::Timeout) if attempt < self.retries => {
+                    attempt += 1;
+                    self.diagnostics.retries += 1;
+                    tracing::warn!("No response to request {id}, retry {attempt}");
+                }
+                res => return res,
+            }
+        }
     }
 
-    async fn read_responce(&mut self) -> Result<protobuf::messages::Response, Error> {
-        let res = tokio::time::timeout(self.timeout, self.io.next()).await;
-        match res {
-            Ok(Some(r)) => r,
-            Ok(None) => Err(Error::UnexpectedEndOfStream),
-            Err(_) => Err(Error::Timeout),
+    /// Ожидать ответ на запрос `id`, ответы на другие запросы отбрасываются
+    async fn read_responce(&mut self, id: u32) -> Result<protobuf::messages::Response, Error> {
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        loop {
+            let res = tokio::time::timeout_at(deadline, self.io.next()).await;
+            if let (Some(capture), Ok(Some(Ok(r)))) = (&self.capture, &res) {
+                capture.record(Direction::Rx, r);
+            }
+            match res {
+                Ok(Some(Ok(r))) if r.id == id => {
+                    self.diagnostics.responses += 1;
+                    return Ok(r);
+                }
+                Ok(Some(Ok(r))) => {
+                    self.diagnostics.stale_frames += 1;
+                    tracing::debug!("Discarding stale response {} (expected {id})", r.id);
+                }
+                Ok(Some(Err(e))) => return Err(e),
+                Ok(None) => return Err(Error::UnexpectedEndOfStream),
+                Err(_) => {
+                    self.diagnostics.timeouts += 1;
+                    return Err(Error::Timeout);
+                }
+            }
         }
     }
 }
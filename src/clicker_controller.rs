@@ -1,51 +1,183 @@
 use std::{
     fmt::Debug,
+    future::Future,
     sync::Arc,
     time::{Duration, SystemTime},
 };
 
 use serde::Serialize;
 use tokio::sync::{
+    broadcast,
     watch::{Receiver, Sender},
-    Mutex,
 };
 
-use crate::{box_plot::BoxPlot, clicker_interface::ClickerInterface};
+use crate::{
+    auto_measure::{auto_measure_task, AutoMeasureState, PresenceDetector},
+    clicker_interface::{ClickerInterface, Diagnostics},
+    measure_strategy::{MeasureStrategy, MeasureTimeout, MeasureTimeouts, Settling, SwitchCycles},
+    segment::{Repeatability, SampleStatus, Segment, SegmentMode, TimedSample},
+    statistics::{Statistics, StatisticsOptions},
+};
+
+/// Причина прерывания измерительного процесса
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum InterruptReason {
+    /// Отменено пользователем
+    Canceled,
+    /// Потеряна связь с устройством
+    DeviceError(String),
+    /// Канал данных от устройства закрыт
+    ChannelClosed,
+    /// Превышено ограничение времени измерения
+    Timeout(MeasureTimeout),
+}
 
-#[derive(Serialize, Debug, Clone, Copy)]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "state", content = "reason")]
 pub enum MeasureProcessState {
     Idle,
     Running,
-    Interrupted,
+    Interrupted(InterruptReason),
     Finished,
 }
 
+/// Состояние подключения к устройству
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "state")]
+pub enum ConnectionState {
+    /// Устройство не подключено
+    Disconnected,
+    /// Устройство подключено и опрашивается
+    Connected,
+    /// Выполняется попытка (пере)подключения
+    Reconnecting { attempt: u32 },
+    /// Связь потеряна, ожидание перед следующей попыткой
+    Failed { error: String },
+}
+
+/// Событие измерительного процесса или подключения к устройству
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event")]
+pub enum ControllerEvent {
+    /// Измерение запущено
+    MeasureStarted,
+    /// Устройство переключило режим во время измерения
+    ModeSwitched { mode: SegmentMode },
+    /// Отсчет переходного процесса после переключения режима отброшен
+    SampleDiscarded { mode: SegmentMode, value: f32 },
+    /// Измерение завершено
+    MeasureFinished,
+    /// Измерение прервано
+    MeasureInterrupted { reason: InterruptReason },
+    /// Ошибка связи с устройством
+    DeviceError { error: String },
+    /// Попытка (пере)подключения к устройству
+    Reconnecting { attempt: u32 },
+    /// Устройство подключено
+    Connected,
+}
+
+/// Сколько событий хранится для отстающего подписчика
+const EVENTS_CAPACITY: usize = 256;
+
 pub struct ClickerController {
+    status_tx: Arc<Sender<MeasureResult>>,
     status_rx: Receiver<MeasureResult>,
+    connection_tx: Arc<Sender<ConnectionState>>,
+    connection_rx: Receiver<ConnectionState>,
+    diagnostics_tx: Arc<Sender<Diagnostics>>,
+    diagnostics_rx: Receiver<Diagnostics>,
+    update_interval: Duration,
+    supervisor_handle: Option<tokio::task::JoinHandle<()>>,
     mc_status_rx: Option<Receiver<MeasureProcessStat>>,
     switch_cycles: u32,
+    timeouts: MeasureTimeouts,
 
     measure_handle: Option<tokio::task::JoinHandle<()>>,
     cancel_tx: Sender<()>,
     cancel_rx: Receiver<()>,
+
+    auto_tx: Arc<Sender<AutoMeasureState>>,
+    auto_rx: Receiver<AutoMeasureState>,
+    auto_handle: Option<tokio::task::JoinHandle<()>>,
+
+    events_tx: broadcast::Sender<ControllerEvent>,
 }
 
 #[derive(Debug)]
 pub struct MeasureResult {
     pub timestamp: SystemTime,
+    /// Время получения по монотонным часам
+    pub instant: tokio::time::Instant,
     pub result: crate::clicker_interface::MeasureResult,
 }
 
+/// Миллисекунды от 1970-01-01 UTC
+fn unix_ms(timestamp: SystemTime) -> f64 {
+    timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+/// Текущий отсчет устройства
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct LiveReading {
+    pub mode: SegmentMode,
+    pub value: f32,
+    /// Время получения, мс от 1970-01-01 UTC
+    pub unix_ms: f64,
+}
+
+impl From<&MeasureResult> for LiveReading {
+    fn from(res: &MeasureResult) -> Self {
+        let value = match res.result {
+            crate::clicker_interface::MeasureResult::Freq(v)
+            | crate::clicker_interface::MeasureResult::Rk(v) => v,
+        };
+        Self {
+            mode: SegmentMode::from(&res.result),
+            value,
+            unix_ms: unix_ms(res.timestamp),
+        }
+    }
+}
+
 #[derive(Serialize, Debug, Clone)]
 pub struct MeasureProcessStat {
     pub timestamp: SystemTime,
+    #[serde(flatten)]
     pub state: MeasureProcessState,
 
     pub freqs: Vec<f32>,
     pub rks: Vec<f32>,
 
-    pub freqs_avg: Option<BoxPlot<f32>>,
-    pub rks_avg: Option<BoxPlot<f32>>,
+    /// Отброшенные отсчеты переходного процесса после переключения режима
+    pub discarded_freqs: Vec<f32>,
+    pub discarded_rks: Vec<f32>,
+
+    /// Фазы измерения в порядке получения
+    pub segments: Vec<Segment>,
+
+    /// Все полученные за время измерения отсчеты с временем получения,
+    /// пустой, пока измерение идет
+    pub samples: Vec<TimedSample>,
+
+    /// Индексы отсчетов `freqs` и `rks`, отброшенных как выбросы
+    pub rejected_freqs: Vec<usize>,
+    pub rejected_rks: Vec<usize>,
+
+    /// Статистика по отсчетам без выбросов
+    pub freqs_avg: Option<Statistics<f32>>,
+    pub rks_avg: Option<Statistics<f32>>,
+
+    /// Статистика по всем отсчетам
+    pub freqs_raw_avg: Option<Statistics<f32>>,
+    pub rks_raw_avg: Option<Statistics<f32>>,
+
+    /// Повторяемость медиан фаз от цикла к циклу
+    pub freqs_repeatability: Option<Repeatability>,
+    pub rks_repeatability: Option<Repeatability>,
 }
 
 impl Default for MeasureProcessStat {
@@ -55,8 +187,18 @@ impl Default for MeasureProcessStat {
             state: MeasureProcessState::Idle,
             freqs: Vec::new(),
             rks: Vec::new(),
+            discarded_freqs: Vec::new(),
+            discarded_rks: Vec::new(),
+            segments: Vec::new(),
+            samples: Vec::new(),
+            rejected_freqs: Vec::new(),
+            rejected_rks: Vec::new(),
             freqs_avg: None,
             rks_avg: None,
+            freqs_raw_avg: None,
+            rks_raw_avg: None,
+            freqs_repeatability: None,
+            rks_repeatability: None,
         }
     }
 }
@@ -64,65 +206,191 @@ impl Default for MeasureProcessStat {
 unsafe impl Send for MeasureProcessStat {}
 
 impl From<(&[f32], &[f32])> for MeasureProcessStat {
-    fn from((freqs, rks): (&[f32], &[f32])) -> Self {
-        let freqs_avg = if freqs.is_empty() {
-            None
-        } else {
-            Some(BoxPlot::new(freqs))
-        };
+    fn from(series: (&[f32], &[f32])) -> Self {
+        Self::from((series.0, series.1, &StatisticsOptions::default()))
+    }
+}
 
-        let rks_avg = if rks.is_empty() {
-            None
-        } else {
-            Some(BoxPlot::new(rks))
+impl From<(&[f32], &[f32], &StatisticsOptions)> for MeasureProcessStat {
+    fn from((freqs, rks, options): (&[f32], &[f32], &StatisticsOptions)) -> Self {
+        let stat = |series: &[f32]| {
+            if series.is_empty() {
+                (None, None, Vec::new())
+            } else {
+                let (cleaned, rejected) = options.outliers.reject(series, options.quantile_method);
+                (
+                    Some(Statistics::with_options(&cleaned, options)),
+                    Some(Statistics::with_options(series, options)),
+                    rejected,
+                )
+            }
         };
+        let (freqs_avg, freqs_raw_avg, rejected_freqs) = stat(freqs);
+        let (rks_avg, rks_raw_avg, rejected_rks) = stat(rks);
 
         Self {
             timestamp: SystemTime::now(),
             state: MeasureProcessState::Running,
             freqs: freqs.to_vec(),
             rks: rks.to_vec(),
+            discarded_freqs: Vec::new(),
+            discarded_rks: Vec::new(),
+            segments: Vec::new(),
+            samples: Vec::new(),
+            rejected_freqs,
+            rejected_rks,
             freqs_avg,
             rks_avg,
+            freqs_raw_avg,
+            rks_raw_avg,
+            freqs_repeatability: None,
+            rks_repeatability: None,
         }
     }
 }
 
 impl ClickerController {
-    pub fn new<E: Debug + Send + 'static, C: ClickerInterface<E> + 'static>(
-        clicker: C,
-        update_interval: Duration,
-        switch_cycles: u32,
-    ) -> Self {
-        let clicker = Arc::new(Mutex::new(clicker));
-
+    /// Создать контроллер без подключенного устройства, см. [`ClickerController::attach`]
+    pub fn new(update_interval: Duration, switch_cycles: u32) -> Self {
         let (status_tx, status_rx) = tokio::sync::watch::channel(MeasureResult {
             timestamp: SystemTime::now(),
+            instant: tokio::time::Instant::now(),
             result: crate::clicker_interface::MeasureResult::Freq(f32::NAN),
         });
 
+        let (connection_tx, connection_rx) =
+            tokio::sync::watch::channel(ConnectionState::Disconnected);
+
+        let (diagnostics_tx, diagnostics_rx) = tokio::sync::watch::channel(Diagnostics::default());
+
         let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(());
 
-        tokio::spawn(read_task(status_tx, clicker.clone(), update_interval));
+        let (auto_tx, auto_rx) = tokio::sync::watch::channel(AutoMeasureState::Disabled);
+
+        let (events_tx, _) = broadcast::channel(EVENTS_CAPACITY);
 
         Self {
+            status_tx: Arc::new(status_tx),
             status_rx,
+            connection_tx: Arc::new(connection_tx),
+            connection_rx,
+            diagnostics_tx: Arc::new(diagnostics_tx),
+            diagnostics_rx,
+            update_interval,
+            supervisor_handle: None,
             mc_status_rx: None,
             switch_cycles,
+            timeouts: MeasureTimeouts::default(),
 
             measure_handle: None,
             cancel_tx,
             cancel_rx,
+
+            auto_tx: Arc::new(auto_tx),
+            auto_rx,
+            auto_handle: None,
+
+            events_tx,
         }
     }
 
+    /// Подключить устройство, открываемое вызовом `connect`.
+    /// Ранее подключенное устройство отключается.
+    /// При потере связи устройство будет переоткрыто повторным вызовом `connect`.
+    pub fn attach<E, C, F, Fut>(&mut self, connect: F)
+    where
+        E: Debug + Send + 'static,
+        C: ClickerInterface<E> + 'static,
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<C, E>> + Send + 'static,
+    {
+        self.detach();
+
+        self.supervisor_handle.replace(tokio::spawn(supervisor_task(
+            self.status_tx.clone(),
+            self.connection_tx.clone(),
+            self.diagnostics_tx.clone(),
+            self.events_tx.clone(),
+            connect,
+            self.update_interval,
+        )));
+    }
+
+    /// Отключить устройство
+    pub fn detach(&mut self) {
+        if let Some(h) = self.supervisor_handle.take() {
+            h.abort();
+            tracing::info!("Clicker detached");
+        }
+        self.connection_tx.send_replace(ConnectionState::Disconnected);
+    }
+
     /// Получить экземпляр рессивера обновленя статуса
     pub fn subscribe(&self) -> Receiver<MeasureResult> {
         self.status_rx.clone()
     }
 
-    // Начать измерительный процесс
+    /// Поток отсчетов устройства не чаще одного за `min_interval`, начиная с последнего полученного.
+    /// Отсчеты, полученные за время ожидания, пропускаются
+    pub fn live(&self, min_interval: Duration) -> impl futures::Stream<Item = LiveReading> {
+        let mut status_rx = self.status_rx.clone();
+        async_stream::stream! {
+            while status_rx.changed().await.is_ok() {
+                let reading = LiveReading::from(&*status_rx.borrow_and_update());
+                yield reading;
+                tokio::time::sleep(min_interval).await;
+            }
+        }
+    }
+
+    /// Текущее состояние подключения к устройству
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection_rx.borrow().clone()
+    }
+
+    /// Счетчики обмена с устройством с момента создания контроллера
+    pub fn diagnostics(&self) -> Diagnostics {
+        *self.diagnostics_rx.borrow()
+    }
+
+    /// Получить экземпляр рессивера обновленя состояния подключения
+    pub fn subscribe_connection_state(&self) -> Receiver<ConnectionState> {
+        self.connection_rx.clone()
+    }
+
+    /// Подписаться на события измерений и подключения к устройству.
+    /// Получаются события, опубликованные после подписки
+    pub fn subscribe_events(&self) -> broadcast::Receiver<ControllerEvent> {
+        self.events_tx.subscribe()
+    }
+
+    /// Ограничения времени последующих измерений, по умолчанию без ограничений
+    pub fn set_timeouts(&mut self, timeouts: MeasureTimeouts) {
+        self.timeouts = timeouts;
+    }
+
+    // Начать измерительный процесс на switch_cycles циклов переключения
     pub fn start_mesure(&mut self) -> Result<(), String> {
+        self.start_mesure_with(
+            Box::new(SwitchCycles::new(self.switch_cycles)),
+            Settling::default(),
+            StatisticsOptions::default(),
+        )
+    }
+
+    /// Начать измерительный процесс со стратегией `strategy`,
+    /// отсчеты переходного процесса после переключения режима отбрасываются согласно `settling`,
+    /// статистика по отсчетам вычисляется с параметрами `statistics`
+    pub fn start_mesure_with(
+        &mut self,
+        strategy: Box<dyn MeasureStrategy>,
+        settling: Settling,
+        statistics: StatisticsOptions,
+    ) -> Result<(), String> {
+        if *self.connection_rx.borrow() != ConnectionState::Connected {
+            return Err("Устройство не подключено!".to_string());
+        }
+
         match &self.mc_status_rx {
             Some(jh) => {
                 match jh.borrow().state {
@@ -140,11 +408,20 @@ impl ClickerController {
             tokio::sync::watch::channel(MeasureProcessStat::default());
         self.mc_status_rx.replace(mc_status_rx);
 
+        // До запуска задачи, чтобы подписчики получили его раньше событий измерения
+        self.events_tx.send(ControllerEvent::MeasureStarted).ok();
         self.measure_handle.replace(tokio::spawn(measure_task(
             self.status_rx.clone(),
+            self.connection_rx.clone(),
             mc_status_tx,
-            self.switch_cycles,
+            MeasureSetup {
+                strategy,
+                settling,
+                statistics,
+                timeouts: self.timeouts,
+            },
             self.cancel_rx.clone(),
+            self.events_tx.clone(),
         )));
 
         Ok(())
@@ -152,33 +429,164 @@ impl ClickerController {
 
     // Прервать измерительный процесс
     pub async fn interrupt_mesure(&mut self) {
+        // Отмена видна подписчикам subscribe_cancel, даже если измерение уже завершено
+        self.cancel_tx.send_replace(());
         if let Some(h) = self.measure_handle.take() {
-            self.cancel_tx.send(()).ok();
             self.mc_status_rx.take();
             let _ = h.await;
         }
     }
 
+    /// Получить экземпляр рессивера отмены измерительного процесса вызовом `interrupt_mesure`.
+    /// Позволяет не начинать следующее измерение серии, если отмена пришла между измерениями
+    pub fn subscribe_cancel(&self) -> Receiver<()> {
+        // клон наследует версию канала, прошлые отмены не должны считаться новыми
+        let mut cancel_rx = self.cancel_rx.clone();
+        cancel_rx.mark_unchanged();
+        cancel_rx
+    }
+
     // Получить экземпляр рессивера обновленя статуса измерительного процесса
     pub fn subscribe_measure_status(&self) -> Option<Receiver<MeasureProcessStat>> {
         self.mc_status_rx.clone()
     }
+
+    /// Включить автоматический запуск: после снятия резонатора и установки нового,
+    /// определяемых `detector`, вызывается `measure`. Следующий резонатор ожидается
+    /// после завершения `measure` и снятия измеренного резонатора.
+    pub async fn start_auto<F, Fut>(&mut self, detector: PresenceDetector, measure: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.stop_auto().await;
+
+        let status_rx = self.status_rx.clone();
+        let auto_tx = self.auto_tx.clone();
+        auto_tx.send_replace(AutoMeasureState::WaitingRemoval);
+        self.auto_handle.replace(tokio::spawn(async move {
+            auto_measure_task(status_rx, &auto_tx, detector, measure).await
+        }));
+    }
+
+    /// Выключить автоматический запуск, незавершенный вызов `measure` прерывается,
+    /// запущенное им измерение отменяется
+    pub async fn stop_auto(&mut self) {
+        let measuring = self.auto_state() == AutoMeasureState::Measuring;
+        if let Some(h) = self.auto_handle.take() {
+            h.abort();
+            let _ = h.await;
+            tracing::info!("Auto measure disabled");
+        }
+        self.auto_tx.send_replace(AutoMeasureState::Disabled);
+
+        if measuring {
+            self.interrupt_mesure().await;
+        }
+    }
+
+    /// Текущее состояние автоматического запуска
+    pub fn auto_state(&self) -> AutoMeasureState {
+        *self.auto_rx.borrow()
+    }
+
+    /// Получить экземпляр рессивера обновленя состояния автоматического запуска
+    pub fn subscribe_auto_state(&self) -> Receiver<AutoMeasureState> {
+        self.auto_rx.clone()
+    }
 }
 
+impl Drop for ClickerController {
+    fn drop(&mut self) {
+        if let Some(h) = self.supervisor_handle.take() {
+            h.abort();
+        }
+        if let Some(h) = self.auto_handle.take() {
+            h.abort();
+        }
+    }
+}
+
+async fn supervisor_task<E, C, F, Fut>(
+    status_tx: Arc<Sender<MeasureResult>>,
+    connection_tx: Arc<Sender<ConnectionState>>,
+    diagnostics_tx: Arc<Sender<Diagnostics>>,
+    events_tx: broadcast::Sender<ControllerEvent>,
+    mut connect: F,
+    update_interval: Duration,
+) where
+    E: Debug + Send,
+    C: ClickerInterface<E>,
+    F: FnMut() -> Fut + Send,
+    Fut: Future<Output = Result<C, E>> + Send,
+{
+    const BACKOFF_MIN: Duration = Duration::from_millis(500);
+    const BACKOFF_MAX: Duration = Duration::from_secs(10);
+
+    let mut attempt = 0;
+    let mut backoff = BACKOFF_MIN;
+
+    loop {
+        attempt += 1;
+        connection_tx.send_replace(ConnectionState::Reconnecting { attempt });
+        events_tx
+            .send(ControllerEvent::Reconnecting { attempt })
+            .ok();
+        let c = match connect().await {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!("Failed to connect to clicker (attempt {attempt}): {e:?}");
+                let error = format!("{e:?}");
+                connection_tx.send_replace(ConnectionState::Failed {
+                    error: error.clone(),
+                });
+                events_tx.send(ControllerEvent::DeviceError { error }).ok();
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(BACKOFF_MAX);
+                continue;
+            }
+        };
+
+        tracing::info!("Clicker connected");
+        attempt = 0;
+        backoff = BACKOFF_MIN;
+        connection_tx.send_replace(ConnectionState::Connected);
+        events_tx.send(ControllerEvent::Connected).ok();
+
+        let base = *diagnostics_tx.borrow();
+        let e = read_task(&status_tx, &diagnostics_tx, base, c, update_interval).await;
+
+        tracing::error!("Connection to clicker lost: {e:?}");
+        let error = format!("{e:?}");
+        connection_tx.send_replace(ConnectionState::Failed {
+            error: error.clone(),
+        });
+        events_tx.send(ControllerEvent::DeviceError { error }).ok();
+        tokio::time::sleep(BACKOFF_MIN).await;
+    }
+}
+
+/// Опрашивать устройство до тех пор, пока не произойдет TRYS ошибок подряд
 async fn read_task<E: Debug + Send, C: ClickerInterface<E>>(
-    status_tx: Sender<MeasureResult>,
-    clicker: Arc<Mutex<C>>,
+    status_tx: &Sender<MeasureResult>,
+    diagnostics_tx: &Sender<Diagnostics>,
+    diagnostics_base: Diagnostics,
+    mut clicker: C,
     update_interval: Duration,
-) {
+) -> E {
     const TRYS: usize = 3;
 
     let mut fails = 0;
     loop {
-        match clicker.lock().await.read().await {
+        let res = clicker.read().await;
+        diagnostics_tx.send_replace(diagnostics_base + clicker.diagnostics());
+
+        match res {
             Ok(result) => {
                 fails = 0;
                 let res: MeasureResult = MeasureResult {
                     timestamp: SystemTime::now(),
+                    instant: tokio::time::Instant::now(),
                     result,
                 };
                 //tracing::trace!("Read from clicker: {:?}", res);
@@ -187,7 +595,7 @@ async fn read_task<E: Debug + Send, C: ClickerInterface<E>>(
             Err(e) => {
                 fails += 1;
                 if fails >= TRYS {
-                    panic!("Fatal error reading from clicker: {:?}", e);
+                    return e;
                 } else {
                     tracing::error!("Error reading from clicker: {:?}", e);
                 }
@@ -197,22 +605,182 @@ async fn read_task<E: Debug + Send, C: ClickerInterface<E>>(
     }
 }
 
+/// Параметры измерительного процесса
+struct MeasureSetup {
+    strategy: Box<dyn MeasureStrategy>,
+    settling: Settling,
+    statistics: StatisticsOptions,
+    timeouts: MeasureTimeouts,
+}
+
+/// Дождаться срока `deadline`, без срока - ждать бесконечно
+async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Данные, собранные измерительным процессом
+struct MeasureData {
+    start: tokio::time::Instant,
+    options: StatisticsOptions,
+    freqs: Vec<f32>,
+    rks: Vec<f32>,
+    discarded_freqs: Vec<f32>,
+    discarded_rks: Vec<f32>,
+    segments: Vec<Segment>,
+    /// Последняя фаза началась с переключения режима
+    segment_from_switch: bool,
+    samples: Vec<TimedSample>,
+}
+
+impl MeasureData {
+    fn new(start: tokio::time::Instant, options: StatisticsOptions) -> Self {
+        Self {
+            start,
+            options,
+            freqs: Vec::new(),
+            rks: Vec::new(),
+            discarded_freqs: Vec::new(),
+            discarded_rks: Vec::new(),
+            segments: Vec::new(),
+            segment_from_switch: false,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Записать отсчет `res` в журнал отсчетов
+    fn log(&mut self, res: &MeasureResult, status: SampleStatus) {
+        let reading = LiveReading::from(res);
+        self.samples.push(TimedSample {
+            mode: reading.mode,
+            value: reading.value,
+            offset_ms: res
+                .instant
+                .saturating_duration_since(self.start)
+                .as_secs_f64()
+                * 1000.0,
+            unix_ms: reading.unix_ms,
+            status,
+        });
+    }
+
+    /// Отсчет не нужен стратегии измерения
+    fn skip(&mut self, res: &MeasureResult) {
+        self.log(res, SampleStatus::Skipped);
+    }
+
+    /// Учесть отсчет, `new_segment` - отсчет относится к новой фазе измерения
+    fn push(&mut self, res: &MeasureResult, new_segment: bool) {
+        self.log(res, SampleStatus::Accepted);
+
+        let sample = &res.result;
+        let mode = SegmentMode::from(sample);
+        let last_mode = self.segments.last().map(|s| s.mode);
+        if new_segment || last_mode != Some(mode) {
+            // предыдущая фаза закончилась переключением, целиком она записана, только если с него и началась
+            if let Some(last) = self.segments.last_mut() {
+                if self.segment_from_switch {
+                    last.close();
+                }
+            }
+            self.segment_from_switch = new_segment || last_mode.is_some();
+            self.segments.push(Segment::new(mode));
+        }
+
+        let value = match *sample {
+            crate::clicker_interface::MeasureResult::Freq(f) => {
+                self.freqs.push(f);
+                f
+            }
+            crate::clicker_interface::MeasureResult::Rk(r) => {
+                self.rks.push(r);
+                r
+            }
+        };
+        if let Some(segment) = self.segments.last_mut() {
+            segment.push(value, &self.options);
+        }
+    }
+
+    /// Отбросить отсчет переходного процесса
+    fn discard(&mut self, res: &MeasureResult) {
+        self.log(res, SampleStatus::Settling);
+
+        match res.result {
+            crate::clicker_interface::MeasureResult::Freq(f) => self.discarded_freqs.push(f),
+            crate::clicker_interface::MeasureResult::Rk(r) => self.discarded_rks.push(r),
+        }
+    }
+
+    fn stat(&self, state: MeasureProcessState) -> MeasureProcessStat {
+        let mut result =
+            MeasureProcessStat::from((self.freqs.as_slice(), self.rks.as_slice(), &self.options));
+        // Журнал отсчетов растет с каждым отсчетом, пока измерение идет, он не передается
+        let with_samples = state != MeasureProcessState::Running;
+        result.state = state;
+        result.discarded_freqs = self.discarded_freqs.clone();
+        result.discarded_rks = self.discarded_rks.clone();
+        result.segments = self.segments.clone();
+
+        if with_samples {
+            result.samples = self.samples.clone();
+
+            // Пометить выбросы в журнале отсчетов, индексы выбросов отсортированы по возрастанию
+            let (mut freq_index, mut rk_index) = (0, 0);
+            let mut rejected_freqs = result.rejected_freqs.iter().peekable();
+            let mut rejected_rks = result.rejected_rks.iter().peekable();
+            for sample in result
+                .samples
+                .iter_mut()
+                .filter(|s| s.status == SampleStatus::Accepted)
+            {
+                let (index, rejected) = match sample.mode {
+                    SegmentMode::Freq => (&mut freq_index, &mut rejected_freqs),
+                    SegmentMode::Rk => (&mut rk_index, &mut rejected_rks),
+                };
+                if rejected.next_if(|&&i| i == *index).is_some() {
+                    sample.status = SampleStatus::Rejected;
+                }
+                *index += 1;
+            }
+        }
+
+        result.freqs_repeatability = Repeatability::new(&self.segments, SegmentMode::Freq);
+        result.rks_repeatability = Repeatability::new(&self.segments, SegmentMode::Rk);
+        result
+    }
+}
+
 async fn measure_task(
     mut status_rx: Receiver<MeasureResult>,
+    mut connection_rx: Receiver<ConnectionState>,
     mc_status_tx: Sender<MeasureProcessStat>,
-    switch_cycles: u32,
+    setup: MeasureSetup,
     mut cancel_rx: Receiver<()>,
+    events_tx: broadcast::Sender<ControllerEvent>,
 ) {
-    let mut freqs = Vec::new();
-    let mut rks = Vec::new();
+    let MeasureSetup {
+        mut strategy,
+        settling,
+        statistics,
+        timeouts,
+    } = setup;
+    let mut data = MeasureData::new(tokio::time::Instant::now(), statistics);
     let mut swiches_count = 0;
     let mut prev_mode_freq = matches!(
         status_rx.borrow().result,
         crate::clicker_interface::MeasureResult::Freq(_)
     );
-    let mut wait_first_switch = true;
+    // Номер отсчета и время последнего переключения режима, пока идет переходный процесс
+    let mut settling_since: Option<(u32, tokio::time::Instant)> = None;
+    let mut last_switch: Option<tokio::time::Instant> = None;
+    // Переключения, о которых стратегия еще не знает
+    let mut pending_switches = 0;
 
     cancel_rx.mark_unchanged(); // нужно сбросить, чтобы не было циклической отмены
+    connection_rx.mark_unchanged();
 
     let send = |result: MeasureProcessStat, swiches_count: u32| {
         tracing::trace!(
@@ -222,62 +790,121 @@ async fn measure_task(
             result.freqs_avg,
             result.rks_avg
         );
+        let event = match &result.state {
+            MeasureProcessState::Finished => Some(ControllerEvent::MeasureFinished),
+            MeasureProcessState::Interrupted(reason) => Some(ControllerEvent::MeasureInterrupted {
+                reason: reason.clone(),
+            }),
+            _ => None,
+        };
         mc_status_tx.send(result).ok();
+        if let Some(event) = event {
+            events_tx.send(event).ok();
+        }
     };
 
     loop {
+        let deadline = timeouts.deadline(data.start, last_switch);
+
         tokio::select! {
             res = status_rx.changed() => {
                 match res {
                     Ok(_) => {
                         let res = status_rx.borrow();
 
-                        if prev_mode_freq
-                            != matches!(res.result, crate::clicker_interface::MeasureResult::Freq(_))
-                        {
-                            wait_first_switch = false;
+                        let switched = prev_mode_freq
+                            != matches!(res.result, crate::clicker_interface::MeasureResult::Freq(_));
+                        if switched {
                             swiches_count += 1;
                             prev_mode_freq = !prev_mode_freq;
-                        } else if wait_first_switch {
-                            continue;
+                            pending_switches += 1;
+                            let now = tokio::time::Instant::now();
+                            settling_since = Some((0, now));
+                            last_switch = Some(now);
+                            events_tx
+                                .send(ControllerEvent::ModeSwitched {
+                                    mode: SegmentMode::from(&res.result),
+                                })
+                                .ok();
                         }
 
-                        match res.result {
-                            crate::clicker_interface::MeasureResult::Freq(f) => {
-                                freqs.push(f);
-                            }
-                            crate::clicker_interface::MeasureResult::Rk(r) => {
-                                rks.push(r);
+                        if let Some((index, switched_at)) = settling_since {
+                            if settling.is_settling(index, switched_at.elapsed()) {
+                                settling_since = Some((index + 1, switched_at));
+                                data.discard(&res);
+                                let reading = LiveReading::from(&*res);
+                                events_tx
+                                    .send(ControllerEvent::SampleDiscarded {
+                                        mode: reading.mode,
+                                        value: reading.value,
+                                    })
+                                    .ok();
+                                send(data.stat(MeasureProcessState::Running), swiches_count);
+                                continue;
                             }
+                            settling_since = None;
                         }
 
-                        let mut result = MeasureProcessStat::from((freqs.as_slice(), rks.as_slice()));
+                        let switches = std::mem::take(&mut pending_switches);
+                        let new_segment = switches > 0;
+                        if !strategy.accept(&res.result, switches) {
+                            data.skip(&res);
+                            continue;
+                        }
 
-                        if swiches_count > switch_cycles * 2 {
-                            result.state = MeasureProcessState::Finished;
-                            send(result, swiches_count);
+                        data.push(&res, new_segment);
+
+                        // Без отсчетов обоих режимов результат не получить, измерение продолжается
+                        let complete = !data.freqs.is_empty()
+                            && !data.rks.is_empty()
+                            && strategy.is_complete(&data.freqs, &data.rks, data.start.elapsed());
+                        if complete {
+                            send(data.stat(MeasureProcessState::Finished), swiches_count);
                             break;
                         } else {
-                            send(result, swiches_count);
+                            send(data.stat(MeasureProcessState::Running), swiches_count);
                         }
                     }
                     Err(e) => {
                         tracing::error!("Error reading from status_rx: {:?}", e);
-                        let mut result = MeasureProcessStat::from((freqs.as_slice(), rks.as_slice()));
-                        result.state = MeasureProcessState::Interrupted;
-                        send(result, swiches_count);
+                        let state = MeasureProcessState::Interrupted(InterruptReason::ChannelClosed);
+                        send(data.stat(state), swiches_count);
                         break;
                     }
                 }
             }
+            res = connection_rx.changed() => {
+                let reason = match res {
+                    Ok(_) => match &*connection_rx.borrow() {
+                        ConnectionState::Connected => continue,
+                        ConnectionState::Disconnected => {
+                            InterruptReason::DeviceError("Устройство отключено".to_string())
+                        }
+                        ConnectionState::Failed { error } => InterruptReason::DeviceError(error.clone()),
+                        ConnectionState::Reconnecting { .. } => {
+                            InterruptReason::DeviceError("Связь с устройством потеряна".to_string())
+                        }
+                    },
+                    Err(_) => InterruptReason::ChannelClosed,
+                };
+                tracing::error!("Measure interrupted: {:?}", reason);
+                send(data.stat(MeasureProcessState::Interrupted(reason)), swiches_count);
+                break;
+            }
+            _ = sleep_until(deadline.map(|(at, _)| at)) => {
+                let timeout = deadline.map(|(_, timeout)| timeout).unwrap_or(MeasureTimeout::Total);
+                tracing::error!("Measure timed out: {:?}", timeout);
+                let state = MeasureProcessState::Interrupted(InterruptReason::Timeout(timeout));
+                send(data.stat(state), swiches_count);
+                break;
+            }
             res = cancel_rx.changed() => {
                 // cancel
                 match res {
                     Ok(_) => {
                         tracing::warn!("Measure was canceled");
-                        let mut result = MeasureProcessStat::from((freqs.as_slice(), rks.as_slice()));
-                        result.state = MeasureProcessState::Interrupted;
-                        send(result, swiches_count);
+                        let state = MeasureProcessState::Interrupted(InterruptReason::Canceled);
+                        send(data.stat(state), swiches_count);
                         break;
                     }
                     Err(e) => {
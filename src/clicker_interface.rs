@@ -1,12 +1,57 @@
 use std::fmt::Debug;
 
+use serde::Serialize;
+
 #[derive(Copy, Clone, Debug)]
 pub enum MeasureResult {
     Rk(f32),
     Freq(f32),
 }
 
+/// Счетчики обмена с устройством
+#[derive(Serialize, Copy, Clone, Debug, Default)]
+pub struct Diagnostics {
+    /// Отправлено запросов
+    pub requests: u64,
+    /// Получено ответов на отправленные запросы
+    pub responses: u64,
+    /// Отброшено устаревших ответов (не на последний запрос)
+    pub stale_frames: u64,
+    /// Запросов без ответа
+    pub timeouts: u64,
+    /// Повторов запросов
+    pub retries: u64,
+    /// Потерь синхронизации с началом кадра
+    pub resync_events: u64,
+    /// Отброшено байт мусора
+    pub dropped_bytes: u64,
+    /// Кадров, которые не удалось декодировать
+    pub decode_errors: u64,
+}
+
+impl std::ops::Add for Diagnostics {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            requests: self.requests + rhs.requests,
+            responses: self.responses + rhs.responses,
+            stale_frames: self.stale_frames + rhs.stale_frames,
+            timeouts: self.timeouts + rhs.timeouts,
+            retries: self.retries + rhs.retries,
+            resync_events: self.resync_events + rhs.resync_events,
+            dropped_bytes: self.dropped_bytes + rhs.dropped_bytes,
+            decode_errors: self.decode_errors + rhs.decode_errors,
+        }
+    }
+}
+
 pub trait ClickerInterface<E: Debug + Send>: Send {
     // read output result from clicker
     fn read(&mut self) -> impl std::future::Future<Output = Result<MeasureResult, E>> + Send;
+
+    // exchange counters, if supported
+    fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::default()
+    }
 }
@@ -1,7 +1,13 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    EmulationProfile, FixedDuration, IqrThreshold, MeasureStrategy, MeasureStrategyKind,
+    MeasureTimeouts, OutlierMethod, OutlierRejection, PresenceDetector, QuantileMethod,
+    RetryPolicy, SampleCount, Settling, StatisticsOptions, SwitchCycles,
+};
+
 fn default_serial_port() -> String {
     "COM1".to_string()
 }
@@ -14,6 +20,70 @@ fn default_cycles() -> u32 {
     3
 }
 
+fn default_retries() -> u32 {
+    1
+}
+
+fn default_samples_per_mode() -> u32 {
+    20
+}
+
+fn default_measure_duration_ms() -> u64 {
+    10000
+}
+
+fn default_freq_iqr_threshold() -> f32 {
+    0.1
+}
+
+fn default_rk_iqr_threshold() -> f32 {
+    2.0
+}
+
+fn default_max_measure_time_ms() -> u64 {
+    30000
+}
+
+fn default_first_switch_timeout_ms() -> u64 {
+    10000
+}
+
+fn default_phase_timeout_ms() -> u64 {
+    30000
+}
+
+fn default_measure_timeout_ms() -> u64 {
+    300000
+}
+
+fn default_auto_freq_min() -> f32 {
+    1000.0
+}
+
+fn default_auto_freq_max() -> f32 {
+    1_000_000.0
+}
+
+fn default_auto_rk_max() -> f32 {
+    1000.0
+}
+
+fn default_auto_confirm_samples() -> u32 {
+    3
+}
+
+fn default_fence_multiplier() -> f64 {
+    StatisticsOptions::default().fence
+}
+
+fn default_confidence_level() -> f64 {
+    StatisticsOptions::default().confidence
+}
+
+fn default_outlier_multiplier() -> f64 {
+    OutlierRejection::default().multiplier
+}
+
 #[derive(Deserialize, Clone, Serialize)]
 pub struct Config {
     #[serde(rename = "RkMeterPort", default = "default_serial_port")]
@@ -24,6 +94,90 @@ pub struct Config {
 
     #[serde(rename = "Cycles", default = "default_cycles")]
     pub cycles: u32,
+
+    #[serde(rename = "Retries", default = "default_retries")]
+    pub retries: u32,
+
+    #[serde(rename = "MeasureStrategy", default)]
+    pub measure_strategy: MeasureStrategyKind,
+
+    #[serde(rename = "SamplesPerMode", default = "default_samples_per_mode")]
+    pub samples_per_mode: u32,
+
+    #[serde(rename = "MeasureDurationMs", default = "default_measure_duration_ms")]
+    pub measure_duration_ms: u64,
+
+    #[serde(rename = "FreqIqrThreshold", default = "default_freq_iqr_threshold")]
+    pub freq_iqr_threshold: f32,
+
+    #[serde(rename = "RkIqrThreshold", default = "default_rk_iqr_threshold")]
+    pub rk_iqr_threshold: f32,
+
+    #[serde(rename = "MaxMeasureTimeMs", default = "default_max_measure_time_ms")]
+    pub max_measure_time_ms: u64,
+
+    #[serde(rename = "SettlingSamples", default)]
+    pub settling_samples: u32,
+
+    #[serde(rename = "SettlingTimeMs", default)]
+    pub settling_time_ms: u64,
+
+    #[serde(
+        rename = "FirstSwitchTimeoutMs",
+        default = "default_first_switch_timeout_ms"
+    )]
+    pub first_switch_timeout_ms: u64,
+
+    #[serde(rename = "PhaseTimeoutMs", default = "default_phase_timeout_ms")]
+    pub phase_timeout_ms: u64,
+
+    #[serde(rename = "MeasureTimeoutMs", default = "default_measure_timeout_ms")]
+    pub measure_timeout_ms: u64,
+
+    #[serde(rename = "QuantileMethod", default)]
+    pub quantile_method: QuantileMethod,
+
+    #[serde(rename = "FenceMultiplier", default = "default_fence_multiplier")]
+    pub fence_multiplier: f64,
+
+    #[serde(rename = "ConfidenceLevel", default = "default_confidence_level")]
+    pub confidence_level: f64,
+
+    #[serde(rename = "OutlierRejection", default)]
+    pub outlier_rejection: OutlierMethod,
+
+    #[serde(rename = "OutlierMultiplier", default = "default_outlier_multiplier")]
+    pub outlier_multiplier: f64,
+
+    #[serde(rename = "MeasureRetries", default)]
+    pub measure_retries: u32,
+
+    #[serde(rename = "RetryFreqIqr", default = "default_freq_iqr_threshold")]
+    pub retry_freq_iqr: f32,
+
+    #[serde(rename = "RetryRkIqr", default = "default_rk_iqr_threshold")]
+    pub retry_rk_iqr: f32,
+
+    #[serde(rename = "AutoMeasure", default)]
+    pub auto_measure: bool,
+
+    #[serde(rename = "AutoFreqMin", default = "default_auto_freq_min")]
+    pub auto_freq_min: f32,
+
+    #[serde(rename = "AutoFreqMax", default = "default_auto_freq_max")]
+    pub auto_freq_max: f32,
+
+    #[serde(rename = "AutoRkMax", default = "default_auto_rk_max")]
+    pub auto_rk_max: f32,
+
+    #[serde(
+        rename = "AutoConfirmSamples",
+        default = "default_auto_confirm_samples"
+    )]
+    pub auto_confirm_samples: u32,
+
+    #[serde(rename = "Emulation", default)]
+    pub emulation: EmulationProfile,
 }
 
 impl Config {
@@ -41,7 +195,11 @@ impl Config {
     }
 
     pub fn load() -> (Self, PathBuf) {
-        let path = Self::get_path();
+        Self::load_from(Self::get_path())
+    }
+
+    /// Загрузить настройки из файла `path`, если файла нет - создать его с настройками по умолчанию
+    pub fn load_from(path: PathBuf) -> (Self, PathBuf) {
         if path.exists() {
             if let Ok(contents) = std::fs::read_to_string(path.clone()) {
                 return (serde_json::from_str::<Config>(&contents).unwrap(), path);
@@ -53,15 +211,81 @@ impl Config {
         tracing::warn!("Failed to read {:?} file! Load default config!", path);
 
         let config: Self = serde_json::from_str("{}").unwrap();
-        config.save();
+        config.save_to(&path);
 
         (config, path)
     }
 
+    /// Стратегия измерения, выбранная в настройках
+    pub fn measure_strategy(&self) -> Box<dyn MeasureStrategy> {
+        use std::time::Duration;
+
+        match self.measure_strategy {
+            MeasureStrategyKind::SwitchCycles => Box::new(SwitchCycles::new(self.cycles)),
+            MeasureStrategyKind::SampleCount => Box::new(SampleCount::new(self.samples_per_mode)),
+            MeasureStrategyKind::Duration => Box::new(FixedDuration::new(Duration::from_millis(
+                self.measure_duration_ms,
+            ))),
+            MeasureStrategyKind::Iqr => Box::new(IqrThreshold::new(
+                self.freq_iqr_threshold,
+                self.rk_iqr_threshold,
+                Duration::from_millis(self.max_measure_time_ms),
+            )),
+        }
+    }
+
+    /// Сколько отсчетов отбрасывать после переключения режима
+    pub fn settling(&self) -> Settling {
+        Settling::new(
+            self.settling_samples,
+            std::time::Duration::from_millis(self.settling_time_ms),
+        )
+    }
+
+    /// Ограничения времени измерения, 0 - без ограничения
+    pub fn timeouts(&self) -> MeasureTimeouts {
+        let limit = |ms: u64| (ms > 0).then(|| std::time::Duration::from_millis(ms));
+        MeasureTimeouts::new(
+            limit(self.first_switch_timeout_ms),
+            limit(self.phase_timeout_ms),
+            limit(self.measure_timeout_ms),
+        )
+    }
+
+    /// Параметры вычисления статистики по отсчетам
+    pub fn statistics(&self) -> StatisticsOptions {
+        StatisticsOptions {
+            quantile_method: self.quantile_method,
+            fence: self.fence_multiplier,
+            confidence: self.confidence_level,
+            outliers: OutlierRejection {
+                method: self.outlier_rejection,
+                multiplier: self.outlier_multiplier,
+            },
+        }
+    }
+
+    /// Повтор измерений с большим разбросом
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::new(self.measure_retries, self.retry_freq_iqr, self.retry_rk_iqr)
+    }
+
+    /// Определение наличия резонатора для автоматического запуска измерений
+    pub fn presence_detector(&self) -> PresenceDetector {
+        PresenceDetector::new(
+            self.auto_freq_min,
+            self.auto_freq_max,
+            self.auto_rk_max,
+            self.auto_confirm_samples,
+        )
+    }
+
     pub fn save(&self) {
-        tracing::debug!("Save settings");
+        self.save_to(&Self::get_path());
+    }
 
-        let path = Self::get_path();
+    pub fn save_to(&self, path: &Path) {
+        tracing::debug!("Save settings");
 
         match std::fs::File::create(path) {
             Ok(f) => serde_json::to_writer_pretty(f, self).expect("Failed to save settings"),
@@ -75,6 +299,32 @@ impl std::fmt::Display for Config {
         writeln!(f, "RkMeterPort: {}", self.rk_meter_port)?;
         writeln!(f, "WebPort: {}", self.web_port)?;
         writeln!(f, "Cycles: {}", self.cycles)?;
+        writeln!(f, "Retries: {}", self.retries)?;
+        writeln!(f, "MeasureStrategy: {}", self.measure_strategy)?;
+        writeln!(f, "SamplesPerMode: {}", self.samples_per_mode)?;
+        writeln!(f, "MeasureDurationMs: {}", self.measure_duration_ms)?;
+        writeln!(f, "FreqIqrThreshold: {}", self.freq_iqr_threshold)?;
+        writeln!(f, "RkIqrThreshold: {}", self.rk_iqr_threshold)?;
+        writeln!(f, "MaxMeasureTimeMs: {}", self.max_measure_time_ms)?;
+        writeln!(f, "SettlingSamples: {}", self.settling_samples)?;
+        writeln!(f, "SettlingTimeMs: {}", self.settling_time_ms)?;
+        writeln!(f, "FirstSwitchTimeoutMs: {}", self.first_switch_timeout_ms)?;
+        writeln!(f, "PhaseTimeoutMs: {}", self.phase_timeout_ms)?;
+        writeln!(f, "MeasureTimeoutMs: {}", self.measure_timeout_ms)?;
+        writeln!(f, "QuantileMethod: {}", self.quantile_method)?;
+        writeln!(f, "FenceMultiplier: {}", self.fence_multiplier)?;
+        writeln!(f, "ConfidenceLevel: {}", self.confidence_level)?;
+        writeln!(f, "OutlierRejection: {}", self.outlier_rejection)?;
+        writeln!(f, "OutlierMultiplier: {}", self.outlier_multiplier)?;
+        writeln!(f, "MeasureRetries: {}", self.measure_retries)?;
+        writeln!(f, "RetryFreqIqr: {}", self.retry_freq_iqr)?;
+        writeln!(f, "RetryRkIqr: {}", self.retry_rk_iqr)?;
+        writeln!(f, "AutoMeasure: {}", self.auto_measure)?;
+        writeln!(f, "AutoFreqMin: {}", self.auto_freq_min)?;
+        writeln!(f, "AutoFreqMax: {}", self.auto_freq_max)?;
+        writeln!(f, "AutoRkMax: {}", self.auto_rk_max)?;
+        writeln!(f, "AutoConfirmSamples: {}", self.auto_confirm_samples)?;
+        writeln!(f, "Emulation: {}", self.emulation)?;
 
         Ok(())
     }
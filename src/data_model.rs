@@ -1,41 +1,107 @@
 use std::vec;
 
 use chrono::{DateTime, Local};
+use serde::Serialize;
 
-use crate::MeasureProcessStat;
+use crate::{
+    MeasureProcessStat, Repeatability, ResonatorCatalog, ResonatorType, Segment, TimedSample,
+    Verdict,
+};
 
 #[derive(Clone)]
 pub struct ResonatorData {
-    ///! Время снятия данных
+    /// Время снятия данных
     pub timestamp: DateTime<Local>,
-    ///! Частота
+    /// Частота
     pub frequency: f32,
-    ///! Отклонение частоты
+    /// Отклонение частоты
     pub frequency_deviation: f32,
-    ///! Значния частоты
+    /// Значния частоты
     pub freqs: Vec<f32>,
-    ///! Динамическое сопротивление
+    /// Частота по всем отсчетам, без отбраковки выбросов
+    pub raw_frequency: f32,
+    /// Отклонение частоты по всем отсчетам
+    pub raw_frequency_deviation: f32,
+    /// Индексы значений частоты, отброшенных как выбросы
+    pub rejected_freqs: Vec<usize>,
+    /// Динамическое сопротивление
     pub rk: f32,
-    ///! Отклонение динамического сопротивления
+    /// Отклонение динамического сопротивления
     pub rk_deviation: f32,
-    ///! Значения динамического сопротивления
+    /// Значения динамического сопротивления
     pub rks: Vec<f32>,
-    ///! Коментарий
+    /// Динамическое сопротивление по всем отсчетам, без отбраковки выбросов
+    pub raw_rk: f32,
+    /// Отклонение динамического сопротивления по всем отсчетам
+    pub raw_rk_deviation: f32,
+    /// Индексы значений динамического сопротивления, отброшенных как выбросы
+    pub rejected_rks: Vec<usize>,
+    /// Отброшенные значения частоты (переходный процесс после переключения режима)
+    pub discarded_freqs: Vec<f32>,
+    /// Отброшенные значения динамического сопротивления
+    pub discarded_rks: Vec<f32>,
+    /// Фазы измерения
+    pub segments: Vec<Segment>,
+    /// Все отсчеты измерения с временем получения
+    pub samples: Vec<TimedSample>,
+    /// Повторяемость частоты от цикла к циклу
+    pub frequency_repeatability: Option<Repeatability>,
+    /// Повторяемость динамического сопротивления от цикла к циклу
+    pub rk_repeatability: Option<Repeatability>,
+    /// Сколько раз выполнялось измерение, сохранена попытка с наименьшим разбросом
+    pub attempts: u32,
+    /// Разброс превышает пороги повтора во всех попытках
+    pub unstable: bool,
+    /// Коментарий
     pub comment: String,
+    /// Заключение о годности по типу партии, нет если тип не найден в каталоге
+    pub verdict: Option<Verdict>,
+    /// Отклонение частоты от номинала партии, нет если номинал неизвестен
+    pub frequency_offset: Option<FrequencyOffset>,
+}
+
+/// Отклонение частоты от номинала
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct FrequencyOffset {
+    /// Гц
+    pub hz: f32,
+    /// Миллионные доли номинала
+    pub ppm: f32,
+}
+
+impl FrequencyOffset {
+    /// Отклонение частоты `frequency` от номинала `nominal`
+    pub fn new(frequency: f32, nominal: f32) -> Self {
+        let hz = frequency - nominal;
+        Self {
+            hz,
+            ppm: hz / nominal * 1e6,
+        }
+    }
+}
+
+impl ResonatorData {
+    /// Оценить годность по типу резонатора `resonator_type` и отклонение от номинала `nominal_freq`
+    pub fn evaluate(&mut self, resonator_type: Option<&ResonatorType>, nominal_freq: Option<f32>) {
+        self.verdict = resonator_type.map(|t| t.evaluate(self));
+        self.frequency_offset = nominal_freq.map(|n| FrequencyOffset::new(self.frequency, n));
+    }
 }
 
 pub struct DataModel {
-    ///! Тип партии резонаторов
+    /// Тип партии резонаторов
     pub data_type: String,
-    ///! Маршрутный лист
+    /// Маршрутный лист
     pub route_id: String,
-    ///! Температура окружающей среды
+    /// Температура окружающей среды
     pub ambient_temperature_range: Vec<f32>,
-    ///! Коментарий к партии
+    /// Коментарий к партии
     pub comment: String,
-    ///! Время снятия данных
+    /// Номинальная частота партии, если не задана - из каталога по типу партии
+    pub nominal_freq: Option<f32>,
+    /// Время снятия данных
     pub timestamp: DateTime<Local>,
-    ///! Данные по резонаторам
+    /// Данные по резонаторам
     pub resonators: Vec<ResonatorData>,
 }
 
@@ -46,26 +112,94 @@ impl Default for DataModel {
             route_id: String::new(),
             ambient_temperature_range: vec![20.0],
             comment: String::new(),
+            nominal_freq: None,
             timestamp: Local::now(),
             resonators: Vec::new(),
         }
     }
 }
 
-impl From<MeasureProcessStat> for ResonatorData {
-    fn from(stat: MeasureProcessStat) -> Self {
-        let freqs_avg = stat.freqs_avg.unwrap();
-        let rks_avg = stat.rks_avg.unwrap();
+impl DataModel {
+    /// Номинальная частота партии: заданная явно или из типа партии в каталоге `catalog`
+    pub fn batch_nominal_freq(&self, catalog: &ResonatorCatalog) -> Option<f32> {
+        self.nominal_freq
+            .or_else(|| catalog.get(&self.data_type).map(|t| t.nominal_freq))
+    }
+
+    /// Оценить резонатор `resonator` по типу и номиналу партии
+    pub fn evaluate_resonator(&self, resonator: &mut ResonatorData, catalog: &ResonatorCatalog) {
+        resonator.evaluate(
+            catalog.get(&self.data_type),
+            self.batch_nominal_freq(catalog),
+        );
+    }
+
+    /// Оценить все резонаторы по типу и номиналу партии
+    pub fn evaluate(&mut self, catalog: &ResonatorCatalog) {
+        let resonator_type = catalog.get(&self.data_type);
+        let nominal_freq = self.batch_nominal_freq(catalog);
+        self.resonators
+            .iter_mut()
+            .for_each(|r| r.evaluate(resonator_type, nominal_freq));
+    }
+
+    /// Среднее отклонение частоты резонаторов партии от номинала
+    pub fn mean_offset(&self) -> Option<FrequencyOffset> {
+        let offsets = self
+            .resonators
+            .iter()
+            .filter_map(|r| r.frequency_offset)
+            .filter(|o| !o.hz.is_nan())
+            .collect::<Vec<_>>();
+        if offsets.is_empty() {
+            return None;
+        }
+
+        let n = offsets.len() as f32;
+        Some(FrequencyOffset {
+            hz: offsets.iter().map(|o| o.hz).sum::<f32>() / n,
+            ppm: offsets.iter().map(|o| o.ppm).sum::<f32>() / n,
+        })
+    }
+}
+
+/// Результат измерения без отсчетов частоты или Rk преобразовать нельзя
+impl TryFrom<MeasureProcessStat> for ResonatorData {
+    type Error = String;
+
+    fn try_from(stat: MeasureProcessStat) -> Result<Self, Self::Error> {
+        let (Some(freqs_avg), Some(freqs_raw_avg)) = (stat.freqs_avg, stat.freqs_raw_avg) else {
+            return Err("Нет отсчетов частоты".to_string());
+        };
+        let (Some(rks_avg), Some(rks_raw_avg)) = (stat.rks_avg, stat.rks_raw_avg) else {
+            return Err("Нет отсчетов Rk".to_string());
+        };
         let timestamp: DateTime<Local> = stat.timestamp.into();
-        Self {
+        Ok(Self {
             timestamp,
             frequency: freqs_avg.median(),
             frequency_deviation: freqs_avg.iqr(),
             freqs: stat.freqs,
+            raw_frequency: freqs_raw_avg.median(),
+            raw_frequency_deviation: freqs_raw_avg.iqr(),
+            rejected_freqs: stat.rejected_freqs,
             rk: rks_avg.median(),
             rk_deviation: rks_avg.iqr(),
+            raw_rk: rks_raw_avg.median(),
+            raw_rk_deviation: rks_raw_avg.iqr(),
+            rejected_rks: stat.rejected_rks,
             comment: String::new(),
             rks: stat.rks,
-        }
+            discarded_freqs: stat.discarded_freqs,
+            discarded_rks: stat.discarded_rks,
+            segments: stat.segments,
+            samples: stat.samples,
+            frequency_repeatability: stat.freqs_repeatability,
+            rk_repeatability: stat.rks_repeatability,
+            attempts: 1,
+            unstable: false,
+            verdict: None,
+            frequency_offset: None,
+        })
     }
 }
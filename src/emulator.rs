@@ -0,0 +1,84 @@
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::Framed;
+
+use crate::{
+    clicker_interface::{ClickerInterface, MeasureResult},
+    protobuf::{
+        messages::{Info, Mode, Request, Response, Status},
+        protobuf_md_codec::ProtobufMDDeviceCodec,
+        Error,
+    },
+    EmulationProfile, FakeClicker,
+};
+
+/// Эмулятор устройства "Щелкунчик" со стороны устройства:
+/// принимает запросы и отвечает на них по протоколу реального устройства.
+/// Значения измерений берутся из [`FakeClicker`]
+pub struct DeviceEmulator {
+    source: FakeClicker,
+}
+
+impl DeviceEmulator {
+    /// Ошибки чтения из `profile` передаются как ответы со статусом ошибки
+    pub fn new(profile: EmulationProfile) -> Self {
+        Self {
+            source: FakeClicker::from_profile(profile),
+        }
+    }
+
+    /// Обслуживать одно подключение до его закрытия
+    pub async fn serve<IO: AsyncRead + AsyncWrite + Unpin>(&mut self, io: IO) -> Result<(), Error> {
+        let mut io = Framed::new(io, ProtobufMDDeviceCodec::default());
+
+        while let Some(req) = io.next().await {
+            let resp = self.process(req?).await;
+            io.send(resp).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn process(&mut self, req: Request) -> Response {
+        let mut resp = Response {
+            id: req.id,
+            device_id: Info::RkMeterId as u32,
+            protocol_version: Info::ProtocolVersion as u32,
+            global_status: Status::Ok as i32,
+            ..Default::default()
+        };
+
+        if req.device_id != Info::RkMeterId as u32
+            || req.protocol_version != Info::ProtocolVersion as u32
+        {
+            tracing::warn!(
+                "Emulator: unexpected device_id={:X} or protocol_version={}",
+                req.device_id,
+                req.protocol_version
+            );
+            resp.global_status = Status::ProtocolError as i32;
+            return resp;
+        }
+
+        if req.get_output_values.is_some() {
+            let output = resp.output.get_or_insert_with(Default::default);
+            match self.source.read().await {
+                Ok(MeasureResult::Rk(rk)) => {
+                    output.current_mode = Some(Mode::Rk as i32);
+                    output.rk = Some(rk);
+                }
+                Ok(MeasureResult::Freq(f)) => {
+                    output.current_mode = Some(Mode::F as i32);
+                    output.freq = Some(f);
+                }
+                Err(_) => {
+                    tracing::debug!("Emulator: injecting error for request {}", req.id);
+                    resp.global_status = Status::ProtocolError as i32;
+                    resp.output = None;
+                }
+            }
+        }
+
+        resp
+    }
+}
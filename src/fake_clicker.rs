@@ -1,57 +1,241 @@
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 
-use rand::distributions::Distribution;
+use rand::{distributions::Distribution, rngs::StdRng, Rng, SeedableRng};
 use rand_distr::Normal;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
 
-use crate::clicker_interface::ClickerInterface;
+use crate::clicker_interface::{ClickerInterface, MeasureResult};
 
+/// Ошибка чтения, внесенная профилем эмуляции
 #[derive(Debug)]
-pub struct NoError;
+pub struct InjectedError;
 
+/// Параметры эмуляции устройства
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct EmulationProfile {
+    /// Номинальная частота, Гц
+    pub nominal_freq: f32,
+    /// Разброс частоты между резонаторами (СКО), Гц
+    pub freq_spread: f32,
+    /// Шум измерения частоты (СКО), Гц
+    pub freq_noise: f32,
+    /// Дрейф частоты, Гц/с
+    pub freq_drift: f32,
+
+    /// Номинальное Rk, Ом
+    pub nominal_rk: f32,
+    /// Разброс Rk между резонаторами (СКО), Ом
+    pub rk_spread: f32,
+    /// Шум измерения Rk (СКО), Ом
+    pub rk_noise: f32,
+    /// Дрейф Rk, Ом/с
+    pub rk_drift: f32,
+
+    /// Вероятность выброса, 0.0..1.0
+    pub outlier_probability: f64,
+    /// Величина выброса в СКО шума
+    pub outlier_scale: f32,
+    /// Вероятность пропуска значения (NaN), 0.0..1.0
+    pub dropout_probability: f64,
+    /// Вероятность ошибки чтения, 0.0..1.0
+    pub error_probability: f64,
+
+    /// Период переключения режимов Rk/F, мс
+    pub switch_period_ms: u64,
+    /// Через сколько мс резонатор заменяется новым, 0 - не заменяется
+    pub resonator_period_ms: u64,
+
+    /// Начальное значение генератора случайных чисел, без него данные не повторяются
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for EmulationProfile {
+    fn default() -> Self {
+        Self {
+            nominal_freq: 32760.0,
+            freq_spread: 5.0,
+            freq_noise: 0.05,
+            freq_drift: 0.0,
+
+            nominal_rk: 50.0,
+            rk_spread: 10.0,
+            rk_noise: 1.0,
+            rk_drift: 0.0,
+
+            outlier_probability: 0.0,
+            outlier_scale: 20.0,
+            dropout_probability: 0.0,
+            error_probability: 0.0,
+
+            switch_period_ms: 1000,
+            resonator_period_ms: 0,
+
+            seed: None,
+        }
+    }
+}
+
+impl EmulationProfile {
+    /// Загрузить профиль из JSON файла `path`, отсутствующие поля берутся по умолчанию
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl std::fmt::Display for EmulationProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "F: {} ±{} Hz (noise {}, drift {}/s), Rk: {} ±{} Ohm (noise {}, drift {}/s), \
+             outliers: {}, dropouts: {}, errors: {}, switch: {} ms, resonator: {} ms",
+            self.nominal_freq,
+            self.freq_spread,
+            self.freq_noise,
+            self.freq_drift,
+            self.nominal_rk,
+            self.rk_spread,
+            self.rk_noise,
+            self.rk_drift,
+            self.outlier_probability,
+            self.dropout_probability,
+            self.error_probability,
+            self.switch_period_ms,
+            self.resonator_period_ms,
+        )
+    }
+}
+
+/// Нормальное распределение с нулевым средним, некорректное СКО считается нулевым
+fn noise(std_dev: f32) -> Normal<f32> {
+    Normal::new(0.0, std_dev.abs()).unwrap_or_else(|_| Normal::new(0.0, 0.0).unwrap())
+}
+
+/// Эмулируемый резонатор
+struct Resonator {
+    freq: f32,
+    rk: f32,
+    installed: Instant,
+}
+
+/// Генератор данных измерений по [`EmulationProfile`].
+/// Время отсчитывается по [`tokio::time::Instant`], поэтому под `tokio::time::pause()`
+/// переключения режимов детерминированы
 pub struct FakeClicker {
-    initial: SystemTime,
-    switch_duration: Duration,
-    distribution_f: Normal<f32>,
-    distribution_rk: Normal<f32>,
-}
-
-impl ClickerInterface<NoError> for FakeClicker {
-    async fn read(&mut self) -> Result<crate::clicker_interface::MeasureResult, NoError> {
-        let mut rng = rand::thread_rng();
-
-        if SystemTime::now()
-            .duration_since(self.initial)
-            .unwrap()
-            .as_secs()
-            / self.switch_duration.as_secs()
-            % 2
-            == 0
+    initial: Instant,
+    profile: EmulationProfile,
+    rng: StdRng,
+    resonator: Resonator,
+}
+
+impl ClickerInterface<InjectedError> for FakeClicker {
+    async fn read(&mut self) -> Result<MeasureResult, InjectedError> {
+        let rng = &mut self.rng;
+        let p = &self.profile;
+
+        if rng.gen_bool(p.error_probability.clamp(0.0, 1.0)) {
+            return Err(InjectedError);
+        }
+
+        let now = Instant::now();
+        if p.resonator_period_ms > 0
+            && now.duration_since(self.resonator.installed)
+                >= Duration::from_millis(p.resonator_period_ms)
         {
-            Ok(crate::clicker_interface::MeasureResult::Rk(
-                self.distribution_rk.sample(&mut rng),
-            ))
+            self.resonator = Self::new_resonator(&self.profile, &mut self.rng, now);
+        }
+
+        let rng = &mut self.rng;
+        let p = &self.profile;
+        let age = now.duration_since(self.resonator.installed).as_secs_f32();
+        let is_rk = (now.duration_since(self.initial).as_millis()
+            / p.switch_period_ms.max(1) as u128)
+            .is_multiple_of(2);
+
+        let (value, noise_sd) = if is_rk {
+            (self.resonator.rk + p.rk_drift * age, p.rk_noise)
         } else {
-            Ok(crate::clicker_interface::MeasureResult::Freq(
-                self.distribution_f.sample(&mut rng),
-            ))
+            (self.resonator.freq + p.freq_drift * age, p.freq_noise)
+        };
+
+        let mut value = value + noise(noise_sd).sample(rng);
+        if rng.gen_bool(p.outlier_probability.clamp(0.0, 1.0)) {
+            let sign = if rng.gen() { 1.0 } else { -1.0 };
+            value += sign * p.outlier_scale * noise_sd.abs();
+        }
+        if rng.gen_bool(p.dropout_probability.clamp(0.0, 1.0)) {
+            value = f32::NAN;
         }
+
+        Ok(if is_rk {
+            MeasureResult::Rk(value)
+        } else {
+            MeasureResult::Freq(value)
+        })
     }
 }
 
 impl FakeClicker {
     pub fn new(switch_duration: Duration) -> Self {
-        const FREQ_CENTER: f32 = 32760.0;
-        const RK_CENTER: f32 = 50.0;
+        Self::from_profile(EmulationProfile {
+            switch_period_ms: switch_duration.as_millis() as u64,
+            ..Default::default()
+        })
+    }
 
+    /// Эмуляция по профилю `profile`
+    pub fn from_profile(profile: EmulationProfile) -> Self {
+        let now = Instant::now();
+        let mut rng = match profile.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
         Self {
-            initial: SystemTime::now(),
-            switch_duration,
-            distribution_f: Normal::new(FREQ_CENTER, 5.0).unwrap(),
-            distribution_rk: Normal::new(RK_CENTER, 10.0).unwrap(),
+            initial: now,
+            resonator: Self::new_resonator(&profile, &mut rng, now),
+            rng,
+            profile,
         }
     }
 
-    pub async fn test(&mut self) -> Result<(), NoError> {
+    /// Задать центральную частоту
+    pub fn with_frequency(mut self, freq: f32) -> Self {
+        self.profile.nominal_freq = freq;
+        self.resonator =
+            Self::new_resonator(&self.profile, &mut self.rng, self.resonator.installed);
+        self
+    }
+
+    /// Задать начальное значение генератора случайных чисел, резонатор генерируется заново
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.profile.seed = Some(seed);
+        self.rng = StdRng::seed_from_u64(seed);
+        self.resonator =
+            Self::new_resonator(&self.profile, &mut self.rng, self.resonator.installed);
+        self
+    }
+
+    pub fn profile(&self) -> &EmulationProfile {
+        &self.profile
+    }
+
+    pub async fn test(&mut self) -> Result<(), InjectedError> {
         Ok(())
     }
+
+    fn new_resonator(
+        profile: &EmulationProfile,
+        rng: &mut StdRng,
+        installed: Instant,
+    ) -> Resonator {
+        Resonator {
+            freq: profile.nominal_freq + noise(profile.freq_spread).sample(rng),
+            rk: profile.nominal_rk + noise(profile.rk_spread).sample(rng),
+            installed,
+        }
+    }
 }
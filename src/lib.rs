@@ -1,18 +1,50 @@
 mod protobuf;
 
+mod auto_measure;
+mod catalog;
 mod clicker;
+mod clicker_controller;
 mod clicker_interface;
 mod config;
+mod emulator;
 mod fake_clicker;
-mod clicker_controller;
+mod measure_feed;
+mod measure_strategy;
+mod port_discovery;
+mod replay_clicker;
+mod segment;
+mod transport;
 
-pub mod box_plot;
+pub mod capture;
 pub mod data_model;
 pub mod data_point;
+pub mod statistics;
 
+pub use auto_measure::{AutoMeasureState, PresenceDetector};
+pub use capture::Capture;
+pub use catalog::{FreqTolerance, ResonatorCatalog, ResonatorType, Verdict};
 pub use config::Config;
 
-pub use clicker_controller::{ClickerController, MeasureProcessStat, MeasureProcessState};
-pub use clicker_interface::ClickerInterface;
-pub use fake_clicker::FakeClicker;
-pub use clicker::Clicker;
\ No newline at end of file
+pub use clicker::Clicker;
+pub use clicker_controller::{
+    ClickerController, ConnectionState, ControllerEvent, InterruptReason, LiveReading,
+    MeasureProcessStat, MeasureProcessState,
+};
+pub use clicker_interface::{ClickerInterface, Diagnostics, MeasureResult};
+pub use emulator::DeviceEmulator;
+pub use fake_clicker::{EmulationProfile, FakeClicker, InjectedError};
+pub use measure_feed::{AttemptStat, MeasureFeed};
+pub use measure_strategy::{
+    BestAttempt, FixedDuration, IqrThreshold, MeasureStrategy, MeasureStrategyKind, MeasureTimeout,
+    MeasureTimeouts, RetryPolicy, SampleCount, Settling, SwitchCycles,
+};
+pub use port_discovery::{available_ports, probe_ports, PortInfo};
+pub use protobuf::protobuf_md_codec::{CodecStats, ProtobufMDCodec, ProtobufMDDeviceCodec};
+pub use replay_clicker::ReplayClicker;
+pub use segment::{
+    Repeatability, SampleStatus, Segment, SegmentMode, TimedSample, MIN_SEGMENT_SAMPLES,
+};
+pub use statistics::{
+    OutlierMethod, OutlierRejection, QuantileMethod, Statistics, StatisticsOptions,
+};
+pub use transport::{Transport, TCP_PREFIX};
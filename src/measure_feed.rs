@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use futures::Stream;
+use serde::Serialize;
+use tokio::sync::watch::Sender;
+
+use crate::clicker_controller::{MeasureProcessStat, MeasureProcessState};
+
+/// Состояние измерения в текущей попытке
+#[derive(Serialize, Debug, Clone)]
+pub struct AttemptStat {
+    /// Номер попытки, с 1
+    pub attempt: u32,
+    /// Всего попыток
+    pub attempts: u32,
+    /// Разброс превышает пороги, измерение будет повторено
+    pub retry: bool,
+    /// Разброс превышает пороги во всех попытках
+    pub unstable: bool,
+    #[serde(flatten)]
+    pub stat: MeasureProcessStat,
+}
+
+impl AttemptStat {
+    /// Последнее состояние измерения, дальше обновлений не будет
+    pub fn is_final(&self) -> bool {
+        match self.stat.state {
+            MeasureProcessState::Interrupted(_) => true,
+            MeasureProcessState::Finished => !self.retry,
+            MeasureProcessState::Idle | MeasureProcessState::Running => false,
+        }
+    }
+}
+
+/// Ход текущего измерения, доступный любому числу клиентов
+#[derive(Clone)]
+pub struct MeasureFeed(Arc<Sender<Option<AttemptStat>>>);
+
+impl Default for MeasureFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MeasureFeed {
+    pub fn new() -> Self {
+        Self(Arc::new(tokio::sync::watch::channel(None).0))
+    }
+
+    /// Последнее состояние измерения, `None` - измерений еще не было
+    pub fn current(&self) -> Option<AttemptStat> {
+        self.0.borrow().clone()
+    }
+
+    /// Измерение идет, к нему можно подключиться
+    pub fn is_running(&self) -> bool {
+        self.0.borrow().as_ref().is_some_and(|s| !s.is_final())
+    }
+
+    pub fn publish(&self, stat: AttemptStat) {
+        self.0.send_replace(Some(stat));
+    }
+
+    /// Обновления состояния до конца измерения, `with_current` - начиная с текущего состояния.
+    /// Промежуточные состояния могут быть пропущены, если клиент не успевает их читать
+    pub fn stream(&self, with_current: bool) -> impl Stream<Item = AttemptStat> {
+        let mut rx = self.0.subscribe();
+        async_stream::stream! {
+            if with_current {
+                let current = rx.borrow_and_update().clone();
+                if let Some(stat) = current {
+                    let done = stat.is_final();
+                    yield stat;
+                    if done {
+                        return;
+                    }
+                }
+            }
+
+            while rx.changed().await.is_ok() {
+                let stat = rx.borrow_and_update().clone();
+                if let Some(stat) = stat {
+                    let done = stat.is_final();
+                    yield stat;
+                    if done {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Ход идущего измерения с текущего состояния, `None` - измерение не идет
+    pub fn progress(&self) -> Option<impl Stream<Item = AttemptStat>> {
+        self.is_running().then(|| self.stream(true))
+    }
+}
@@ -0,0 +1,323 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{clicker_interface::MeasureResult, statistics::Statistics};
+
+/// Стратегия измерения: какие отсчеты учитывать и когда завершить измерение
+pub trait MeasureStrategy: Send {
+    /// Учитывать ли отсчет `sample`, `switches` - сколько раз режим устройства переключился
+    /// после предыдущего переданного стратегии отсчета, включая переключения во время переходного процесса
+    fn accept(&mut self, sample: &MeasureResult, switches: u32) -> bool;
+
+    /// Измерение завершено: собраны `freqs` и `rks`, с начала измерения прошло `elapsed`.
+    /// Пока нет отсчетов обоих режимов, измерение продолжается независимо от результата
+    fn is_complete(&self, freqs: &[f32], rks: &[f32], elapsed: Duration) -> bool;
+}
+
+/// Отбрасывание отсчетов переходного процесса после переключения режима Rk/F.
+/// Отсчет отбрасывается, пока не набрано `samples` отсчетов или не прошло `time` с переключения
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Settling {
+    pub samples: u32,
+    pub time: Duration,
+}
+
+impl Settling {
+    pub fn new(samples: u32, time: Duration) -> Self {
+        Self { samples, time }
+    }
+
+    /// Отсчет номер `index` после переключения, полученный через `elapsed`, нужно отбросить
+    pub fn is_settling(&self, index: u32, elapsed: Duration) -> bool {
+        index < self.samples || elapsed < self.time
+    }
+}
+
+/// Выбор стратегии измерения в настройках
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum MeasureStrategyKind {
+    /// Заданное число циклов переключения Rk -> F -> Rk, см. [`SwitchCycles`]
+    #[default]
+    SwitchCycles,
+    /// Заданное число отсчетов в каждом режиме, см. [`SampleCount`]
+    SampleCount,
+    /// Заданное время, см. [`FixedDuration`]
+    Duration,
+    /// До снижения разброса ниже порогов, см. [`IqrThreshold`]
+    Iqr,
+}
+
+impl std::fmt::Display for MeasureStrategyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Ожидать первого переключения режима, затем собирать данные `cycles` циклов Rk -> F -> Rk
+pub struct SwitchCycles {
+    cycles: u32,
+    switches: u32,
+}
+
+impl SwitchCycles {
+    pub fn new(cycles: u32) -> Self {
+        Self {
+            cycles,
+            switches: 0,
+        }
+    }
+}
+
+impl MeasureStrategy for SwitchCycles {
+    fn accept(&mut self, _sample: &MeasureResult, switches: u32) -> bool {
+        self.switches += switches;
+        self.switches > 0
+    }
+
+    fn is_complete(&self, _freqs: &[f32], _rks: &[f32], _elapsed: Duration) -> bool {
+        self.switches > self.cycles * 2
+    }
+}
+
+/// Собрать `samples` отсчетов в каждом режиме, лишние отсчеты не учитываются
+pub struct SampleCount {
+    samples: usize,
+    freqs: usize,
+    rks: usize,
+}
+
+impl SampleCount {
+    pub fn new(samples: u32) -> Self {
+        Self {
+            samples: samples as usize,
+            freqs: 0,
+            rks: 0,
+        }
+    }
+}
+
+impl MeasureStrategy for SampleCount {
+    fn accept(&mut self, sample: &MeasureResult, _switches: u32) -> bool {
+        let count = match sample {
+            MeasureResult::Freq(_) => &mut self.freqs,
+            MeasureResult::Rk(_) => &mut self.rks,
+        };
+        if *count < self.samples {
+            *count += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_complete(&self, freqs: &[f32], rks: &[f32], _elapsed: Duration) -> bool {
+        freqs.len() >= self.samples && rks.len() >= self.samples
+    }
+}
+
+/// Собирать все отсчеты в течение `duration`.
+/// Если `duration` меньше периода переключения устройства, измерение продолжится до отсчета второго режима
+pub struct FixedDuration {
+    duration: Duration,
+}
+
+impl FixedDuration {
+    pub fn new(duration: Duration) -> Self {
+        Self { duration }
+    }
+}
+
+impl MeasureStrategy for FixedDuration {
+    fn accept(&mut self, _sample: &MeasureResult, _switches: u32) -> bool {
+        true
+    }
+
+    fn is_complete(&self, _freqs: &[f32], _rks: &[f32], elapsed: Duration) -> bool {
+        elapsed >= self.duration
+    }
+}
+
+/// Собирать отсчеты, пока межквартильный размах частоты и Rk не станет меньше порогов,
+/// но не дольше `max_time`
+pub struct IqrThreshold {
+    freq_iqr: f32,
+    rk_iqr: f32,
+    min_samples: usize,
+    max_time: Duration,
+}
+
+impl IqrThreshold {
+    /// Меньше отсчетов в режиме размах не оценивается
+    pub const MIN_SAMPLES: usize = 5;
+
+    pub fn new(freq_iqr: f32, rk_iqr: f32, max_time: Duration) -> Self {
+        Self {
+            freq_iqr,
+            rk_iqr,
+            min_samples: Self::MIN_SAMPLES,
+            max_time,
+        }
+    }
+
+    fn is_stable(series: &[f32], threshold: f32, min_samples: usize) -> bool {
+        series.len() >= min_samples && Statistics::new(series).iqr() <= threshold
+    }
+}
+
+impl MeasureStrategy for IqrThreshold {
+    fn accept(&mut self, _sample: &MeasureResult, _switches: u32) -> bool {
+        true
+    }
+
+    fn is_complete(&self, freqs: &[f32], rks: &[f32], elapsed: Duration) -> bool {
+        elapsed >= self.max_time
+            || (Self::is_stable(freqs, self.freq_iqr, self.min_samples)
+                && Self::is_stable(rks, self.rk_iqr, self.min_samples))
+    }
+}
+
+/// Повтор измерения с большим разбросом: до `retries` повторов,
+/// пока межквартильный размах частоты или Rk превышает порог `freq_iqr` / `rk_iqr`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Сколько раз повторить измерение, 0 - не повторять
+    pub retries: u32,
+    pub freq_iqr: f32,
+    pub rk_iqr: f32,
+}
+
+impl RetryPolicy {
+    pub fn new(retries: u32, freq_iqr: f32, rk_iqr: f32) -> Self {
+        Self {
+            retries,
+            freq_iqr,
+            rk_iqr,
+        }
+    }
+
+    /// Повтор включен
+    pub fn is_enabled(&self) -> bool {
+        self.retries > 0
+    }
+
+    /// Всего попыток измерения
+    pub fn attempts(&self) -> u32 {
+        self.retries.saturating_add(1)
+    }
+
+    /// Превышение разброса: наибольшее отношение межквартильного размаха к порогу.
+    /// Не больше 1 - измерение стабильное, если разброс неизвестен - бесконечность
+    pub fn excess(&self, freq_iqr: f32, rk_iqr: f32) -> f32 {
+        let freq = freq_iqr / self.freq_iqr;
+        let rk = rk_iqr / self.rk_iqr;
+        if freq.is_nan() || rk.is_nan() {
+            f32::INFINITY
+        } else {
+            freq.max(rk)
+        }
+    }
+
+    /// Разброс не превышает порогов
+    pub fn is_stable(&self, freq_iqr: f32, rk_iqr: f32) -> bool {
+        self.excess(freq_iqr, rk_iqr) <= 1.0
+    }
+}
+
+/// Выбор попытки измерения с наименьшим превышением разброса по [`RetryPolicy`]
+#[derive(Debug)]
+pub struct BestAttempt<T> {
+    policy: RetryPolicy,
+    best: Option<(f32, T)>,
+}
+
+impl<T> BestAttempt<T> {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy, best: None }
+    }
+
+    /// Учесть результат попытки `result` с межквартильными размахами `freq_iqr` и `rk_iqr`,
+    /// возвращает превышение разброса этой попытки
+    pub fn push(&mut self, result: T, freq_iqr: f32, rk_iqr: f32) -> f32 {
+        let excess = self.policy.excess(freq_iqr, rk_iqr);
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|(best_excess, _)| excess < *best_excess)
+        {
+            self.best = Some((excess, result));
+        }
+        excess
+    }
+
+    /// Повторить измерение после завершенной попытки номер `attempt` (с 1) с превышением разброса `excess`
+    pub fn need_retry(&self, attempt: u32, excess: f32) -> bool {
+        excess > 1.0 && attempt < self.policy.attempts()
+    }
+
+    /// Забрать лучшую попытку и признак нестабильности: повтор включен, последняя попытка
+    /// завершена (`finished`), а не прервана, и разброс даже лучшей попытки превышает пороги
+    pub fn take(&mut self, finished: bool) -> Option<(T, bool)> {
+        self.best.take().map(|(excess, result)| {
+            let unstable = finished && self.policy.is_enabled() && excess > 1.0;
+            (result, unstable)
+        })
+    }
+}
+
+/// Превышенное ограничение времени измерения
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum MeasureTimeout {
+    /// Режим устройства не переключился с начала измерения
+    FirstSwitch,
+    /// Режим устройства не переключается слишком долго
+    Phase,
+    /// Измерение идет слишком долго
+    Total,
+}
+
+/// Ограничения времени измерения, `None` - без ограничения.
+/// `first_switch` - с начала измерения до первого переключения режима,
+/// `max_phase` - между переключениями режима, `max_total` - всего измерения
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeasureTimeouts {
+    pub first_switch: Option<Duration>,
+    pub max_phase: Option<Duration>,
+    pub max_total: Option<Duration>,
+}
+
+impl MeasureTimeouts {
+    pub fn new(
+        first_switch: Option<Duration>,
+        max_phase: Option<Duration>,
+        max_total: Option<Duration>,
+    ) -> Self {
+        Self {
+            first_switch,
+            max_phase,
+            max_total,
+        }
+    }
+
+    /// Ближайший срок для измерения, начатого в `start`, с последним переключением режима в `last_switch`
+    pub fn deadline(
+        &self,
+        start: tokio::time::Instant,
+        last_switch: Option<tokio::time::Instant>,
+    ) -> Option<(tokio::time::Instant, MeasureTimeout)> {
+        let phase = match last_switch {
+            None => self
+                .first_switch
+                .map(|t| (start + t, MeasureTimeout::FirstSwitch)),
+            Some(switched_at) => self
+                .max_phase
+                .map(|t| (switched_at + t, MeasureTimeout::Phase)),
+        };
+        let total = self.max_total.map(|t| (start + t, MeasureTimeout::Total));
+
+        match (phase, total) {
+            (Some(phase), Some(total)) => Some(if total.0 <= phase.0 { total } else { phase }),
+            (phase, total) => phase.or(total),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio_serial::SerialPortType;
+
+use crate::{protobuf::Error, Clicker};
+
+/// Описание последовательного порта системы
+#[derive(Serialize, Debug, Clone)]
+pub struct PortInfo {
+    /// Имя порта (COM3, /dev/ttyUSB0, ...)
+    pub name: String,
+    /// Тип порта: USB, PCI, Bluetooth, Unknown
+    pub port_type: String,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    /// Отвечает ли на порту устройство "Щелкунчик", None - порт не проверялся
+    pub is_clicker: Option<bool>,
+    /// Ошибка при проверке порта
+    pub probe_error: Option<String>,
+}
+
+impl From<tokio_serial::SerialPortInfo> for PortInfo {
+    fn from(info: tokio_serial::SerialPortInfo) -> Self {
+        let mut res = Self {
+            name: info.port_name,
+            port_type: String::new(),
+            vid: None,
+            pid: None,
+            serial_number: None,
+            manufacturer: None,
+            product: None,
+            is_clicker: None,
+            probe_error: None,
+        };
+
+        res.port_type = match info.port_type {
+            SerialPortType::UsbPort(usb) => {
+                res.vid = Some(usb.vid);
+                res.pid = Some(usb.pid);
+                res.serial_number = usb.serial_number;
+                res.manufacturer = usb.manufacturer;
+                res.product = usb.product;
+                "USB"
+            }
+            SerialPortType::PciPort => "PCI",
+            SerialPortType::BluetoothPort => "Bluetooth",
+            SerialPortType::Unknown => "Unknown",
+        }
+        .to_string();
+
+        res
+    }
+}
+
+/// Получить список последовательных портов системы
+pub fn available_ports() -> Result<Vec<PortInfo>, Error> {
+    let ports = tokio_serial::available_ports().map_err(std::io::Error::from)?;
+    Ok(ports.into_iter().map(PortInfo::from).collect())
+}
+
+/// Проверить, на каких портах отвечает устройство "Щелкунчик".
+/// Порты проверяются параллельно
+pub async fn probe_ports(ports: Vec<PortInfo>, timeout: Duration) -> Vec<PortInfo> {
+    futures::future::join_all(ports.into_iter().map(|mut port| async move {
+        match Clicker::connect(port.name.clone(), timeout, 0).await {
+            Ok(_) => port.is_clicker = Some(true),
+            Err(e) => {
+                port.is_clicker = Some(false);
+                port.probe_error = Some(format!("{:?}", e));
+            }
+        }
+        port
+    }))
+    .await
+}
@@ -1,47 +1,186 @@
 use bytes::{Buf, BufMut, BytesMut};
 
 use prost::Message;
+use serde::Serialize;
 use tokio_util::codec::{Decoder, Encoder};
 
 use super::messages::{Request, Response};
 
-pub(crate) struct ProtobufMDCodec;
+/// Максимальная длина кадра, кадр с большей длиной считается мусором
+const MAX_FRAME_LEN: u64 = 256;
 
-impl Decoder for ProtobufMDCodec {
-    type Item = Response;
-    type Error = super::Error;
+/// Счетчики декодера
+#[derive(Serialize, Copy, Clone, Debug, Default, PartialEq)]
+pub struct CodecStats {
+    /// Сколько раз декодер терял синхронизацию с началом кадра
+    pub resync_events: u64,
+    /// Отброшено байт мусора
+    pub dropped_bytes: u64,
+    /// Кадров, которые не удалось декодировать
+    pub decode_errors: u64,
+}
 
-    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        loop {
-            if src.is_empty() {
-                return Ok(None);
-            }
+/// Кодек стороны хоста: отправляет [`Request`], принимает [`Response`]
+#[derive(Default)]
+pub struct ProtobufMDCodec {
+    deframer: Deframer,
+}
+
+/// Кодек стороны устройства: принимает [`Request`], отправляет [`Response`]
+#[derive(Default)]
+pub struct ProtobufMDDeviceCodec {
+    deframer: Deframer,
+}
+
+enum Varint {
+    Value { value: u64, len: usize },
+    Incomplete,
+    Invalid,
+}
+
+fn parse_varint(buf: &[u8]) -> Varint {
+    let mut value = 0u64;
+    for (i, b) in buf.iter().take(10).enumerate() {
+        value |= ((b & 0x7f) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            return Varint::Value { value, len: i + 1 };
+        }
+    }
 
-            if src[0] == super::messages::Info::Magick as u8 {
-                break;
+    if buf.len() < 10 {
+        Varint::Incomplete
+    } else {
+        Varint::Invalid
+    }
+}
+
+const MAGICK: u8 = super::messages::Info::Magick as u8;
+
+/// Кадр в начале буфера, начинающегося с Magick
+enum Frame {
+    /// Сообщение занимает байты `start..end`
+    Complete {
+        start: usize,
+        end: usize,
+    },
+    Incomplete,
+    Invalid,
+}
+
+fn parse_frame(src: &[u8]) -> Frame {
+    match parse_varint(&src[1..]) {
+        Varint::Incomplete => Frame::Incomplete,
+        Varint::Value { value, len } if value <= MAX_FRAME_LEN => {
+            let start = 1 + len;
+            let end = start + value as usize;
+            if src.len() < end {
+                Frame::Incomplete
             } else {
-                src.advance(1);
+                Frame::Complete { start, end }
+            }
+        }
+        _ => Frame::Invalid,
+    }
+}
+
+/// Позиция следующего после начала `src` Magick, с которого начинается целый кадр
+/// с непустым декодируемым сообщением
+fn next_frame<M: Message + Default>(src: &[u8]) -> Option<usize> {
+    (1..src.len())
+        .filter(|&pos| src[pos] == MAGICK)
+        .find(|&pos| match parse_frame(&src[pos..]) {
+            Frame::Complete { start, end } => {
+                end > start && M::decode(&src[pos + start..pos + end]).is_ok()
             }
+            _ => false,
+        })
+}
+
+/// Выделяет из потока кадры вида <Magick><length-delimited protobuf>,
+/// при ошибке пропускает байты до следующего Magick
+#[derive(Default)]
+struct Deframer {
+    stats: CodecStats,
+    lost_sync: bool,
+}
+
+impl Deframer {
+    fn drop_bytes(&mut self, src: &mut BytesMut, count: usize) {
+        if !self.lost_sync {
+            self.lost_sync = true;
+            self.stats.resync_events += 1;
         }
+        self.stats.dropped_bytes += count as u64;
+        src.advance(count);
+    }
 
-        let remaning = src.remaining();
-        let mut buf: BytesMut = src.clone();
-        buf.advance(1); // skip magick
+    fn decode<M: Message + Default>(&mut self, src: &mut BytesMut) -> Option<M> {
+        loop {
+            let garbage = src.iter().position(|b| *b == MAGICK).unwrap_or(src.len());
+            if garbage > 0 {
+                self.drop_bytes(src, garbage);
+            }
 
-        match super::messages::Response::decode_length_delimited(&mut buf) {
-            Ok(msg) => {
-                src.advance(remaning - buf.remaining());
-                Ok(Some(msg))
+            if src.is_empty() {
+                return None;
             }
-            Err(e) => {
-                if (e == prost::DecodeError::new("buffer underflow")) || buf.is_empty() {
-                    Ok(None)
-                } else {
-                    Err(e.into())
+
+            match parse_frame(src) {
+                Frame::Complete { start, end } => match M::decode(&src[start..end]) {
+                    Ok(msg) => {
+                        src.advance(end);
+                        self.lost_sync = false;
+                        return Some(msg);
+                    }
+                    Err(e) => {
+                        tracing::debug!("Failed to decode frame: {}", e);
+                        self.stats.decode_errors += 1;
+                    }
+                },
+                Frame::Incomplete => {
+                    // Кадр еще не пришел целиком. Если за ним в буфере уже есть целый кадр,
+                    // то Magick ложный: ответа на запрос не дождаться, нужно синхронизироваться сейчас
+                    let pos = next_frame::<M>(src)?;
+                    self.stats.decode_errors += 1;
+                    self.drop_bytes(src, pos);
+                    continue;
                 }
+                Frame::Invalid => self.stats.decode_errors += 1,
             }
+
+            // Ложный Magick, пропустить его и искать следующий
+            self.drop_bytes(src, 1);
         }
     }
+}
+
+fn encode_frame<M: Message>(msg: M, buf: &mut BytesMut) -> Result<(), super::Error> {
+    buf.put_u8(super::messages::Info::Magick as u8);
+
+    msg.encode_length_delimited(buf)?;
+
+    Ok(())
+}
+
+impl ProtobufMDCodec {
+    pub fn stats(&self) -> CodecStats {
+        self.deframer.stats
+    }
+}
+
+impl ProtobufMDDeviceCodec {
+    pub fn stats(&self) -> CodecStats {
+        self.deframer.stats
+    }
+}
+
+impl Decoder for ProtobufMDCodec {
+    type Item = Response;
+    type Error = super::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.deframer.decode(src))
+    }
 
     fn framed<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Sized>(
         self,
@@ -58,10 +197,23 @@ impl Encoder<Request> for ProtobufMDCodec {
     type Error = super::Error;
 
     fn encode(&mut self, req_type: Request, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        buf.put_u8(super::messages::Info::Magick as u8);
+        encode_frame(req_type, buf)
+    }
+}
+
+impl Decoder for ProtobufMDDeviceCodec {
+    type Item = Request;
+    type Error = super::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(self.deframer.decode(src))
+    }
+}
 
-        req_type.encode_length_delimited(buf)?;
+impl Encoder<Response> for ProtobufMDDeviceCodec {
+    type Error = super::Error;
 
-        Ok(())
+    fn encode(&mut self, resp: Response, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        encode_frame(resp, buf)
     }
 }
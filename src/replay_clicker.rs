@@ -0,0 +1,83 @@
+use std::{collections::HashSet, path::Path, time::Duration};
+
+use prost::Message;
+
+use crate::{
+    capture::{from_hex, read_capture, Direction},
+    clicker,
+    clicker_interface::{ClickerInterface, MeasureResult},
+    protobuf::{
+        messages::{Request, Response},
+        Error,
+    },
+};
+
+/// Воспроизведение ответов устройства из файла захвата, см. [`crate::Capture`]
+pub struct ReplayClicker {
+    frames: std::vec::IntoIter<(Duration, Response)>,
+    speed: f64,
+    /// Время воспроизведения первого кадра и его смещение в записи
+    start: Option<(tokio::time::Instant, Duration)>,
+}
+
+impl ClickerInterface<Error> for ReplayClicker {
+    async fn read(&mut self) -> Result<MeasureResult, Error> {
+        let (offset, resp) = self.frames.next().ok_or(Error::UnexpectedEndOfStream)?;
+
+        if self.speed > 0.0 {
+            let (start, first) = *self
+                .start
+                .get_or_insert_with(|| (tokio::time::Instant::now(), offset));
+            let delay = offset.saturating_sub(first).div_f64(self.speed);
+            tokio::time::sleep_until(start + delay).await;
+        }
+
+        clicker::response_to_result(resp)
+    }
+}
+
+impl ReplayClicker {
+    /// Загрузить ответы устройства на запросы измеренных значений из файла захвата `path`,
+    /// ответы на остальные запросы (проверка связи) пропускаются.
+    /// * `speed` - ускорение воспроизведения относительно записи, 0 - без задержек
+    pub fn open<P: AsRef<Path>>(path: P, speed: f64) -> Result<Self, Error> {
+        let mut output_requests = HashSet::new();
+        let mut frames = Vec::new();
+        for r in read_capture(path)? {
+            let frame = from_hex(&r.frame).ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid frame hex")
+            })?;
+            match r.direction {
+                Direction::Tx => {
+                    let req = Request::decode(frame.as_slice())?;
+                    if req.get_output_values.is_some() {
+                        output_requests.insert(req.id);
+                    }
+                }
+                Direction::Rx => {
+                    let resp = Response::decode(frame.as_slice())?;
+                    if output_requests.contains(&resp.id) {
+                        frames.push((Duration::from_micros(r.offset_us), resp));
+                    }
+                }
+            }
+        }
+
+        tracing::info!("Replay: loaded {} responses", frames.len());
+
+        Ok(Self {
+            frames: frames.into_iter(),
+            speed,
+            start: None,
+        })
+    }
+
+    /// Открыть файл захвата, проверяя наличие в нем ответов устройства
+    pub async fn connect(path: String, speed: f64) -> Result<Self, Error> {
+        let replay = Self::open(path, speed)?;
+        if replay.frames.len() == 0 {
+            return Err(Error::UnexpectedEndOfStream);
+        }
+        Ok(replay)
+    }
+}
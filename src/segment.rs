@@ -0,0 +1,125 @@
+use serde::Serialize;
+
+use crate::{
+    clicker_interface::MeasureResult,
+    statistics::{Statistics, StatisticsOptions},
+};
+
+/// Режим устройства во время фазы измерения
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum SegmentMode {
+    Freq,
+    Rk,
+}
+
+impl From<&MeasureResult> for SegmentMode {
+    fn from(value: &MeasureResult) -> Self {
+        match value {
+            MeasureResult::Freq(_) => Self::Freq,
+            MeasureResult::Rk(_) => Self::Rk,
+        }
+    }
+}
+
+/// Что сделано с отсчетом при измерении
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum SampleStatus {
+    /// Учтен в результате
+    Accepted,
+    /// Отброшен как переходный процесс после переключения режима
+    Settling,
+    /// Не нужен стратегии измерения
+    Skipped,
+    /// Учтен стратегией, но отброшен как выброс
+    Rejected,
+}
+
+/// Отсчет с временем получения
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct TimedSample {
+    pub mode: SegmentMode,
+    pub value: f32,
+    /// Время с начала измерения по монотонным часам, мс
+    pub offset_ms: f64,
+    /// Системное время получения, мс с начала эпохи UNIX
+    pub unix_ms: f64,
+    pub status: SampleStatus,
+}
+
+/// Минимальное число отсчетов фазы, учитываемой в повторяемости
+pub const MIN_SEGMENT_SAMPLES: usize = 2;
+
+/// Отсчеты одной фазы измерения: от переключения режима до следующего переключения
+#[derive(Serialize, Debug, Clone)]
+pub struct Segment {
+    pub mode: SegmentMode,
+    pub values: Vec<f32>,
+    pub stat: Statistics<f32>,
+    /// Фаза записана целиком: началась и закончилась переключением режима
+    pub complete: bool,
+}
+
+impl Segment {
+    pub fn new(mode: SegmentMode) -> Self {
+        Self {
+            mode,
+            values: Vec::new(),
+            stat: Statistics::new(&[]),
+            complete: false,
+        }
+    }
+
+    pub fn push(&mut self, value: f32, options: &StatisticsOptions) {
+        self.values.push(value);
+        self.stat = Statistics::with_options(&self.values, options);
+    }
+
+    /// Отметить фазу записанной целиком
+    pub fn close(&mut self) {
+        self.complete = true;
+    }
+}
+
+/// Повторяемость от цикла к циклу: разброс медиан фаз одного режима
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Repeatability {
+    /// Число учтенных фаз
+    pub cycles: usize,
+    /// Размах медиан фаз (max - min)
+    pub range: f32,
+    /// СКО медиан фаз
+    pub std_dev: f32,
+}
+
+impl Repeatability {
+    /// Повторяемость по фазам `segments` режима `mode`.
+    /// Учитываются только фазы, записанные целиком и содержащие не менее [`MIN_SEGMENT_SAMPLES`] отсчетов,
+    /// если таких фаз меньше двух - `None`
+    pub fn new(segments: &[Segment], mode: SegmentMode) -> Option<Self> {
+        let medians = segments
+            .iter()
+            .filter(|s| s.mode == mode && s.complete && s.values.len() >= MIN_SEGMENT_SAMPLES)
+            .map(|s| s.stat.median())
+            .filter(|m| !m.is_nan())
+            .collect::<Vec<_>>();
+
+        if medians.len() < 2 {
+            return None;
+        }
+
+        let n = medians.len() as f32;
+        let mean = medians.iter().sum::<f32>() / n;
+        let variance = medians.iter().map(|m| (m - mean).powi(2)).sum::<f32>() / (n - 1.0);
+        let (min, max) = medians
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &m| {
+                (min.min(m), max.max(m))
+            });
+
+        Some(Self {
+            cycles: medians.len(),
+            range: max - min,
+            std_dev: variance.sqrt(),
+        })
+    }
+}
@@ -0,0 +1,394 @@
+use num_traits::{Float, FromPrimitive};
+use serde::{Deserialize, Serialize};
+
+/// Способ вычисления квантилей по выборке, названия как в numpy.
+/// Дискретные способы берут элемент(ы) выборки с индексом около `p * (n - 1)`,
+/// непрерывные - интерполируют между соседними элементами (Hyndman & Fan, 1996)
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum QuantileMethod {
+    /// Ближайший меньший элемент
+    Lower,
+    /// Ближайший больший элемент
+    Higher,
+    /// Ближайший элемент
+    Nearest,
+    /// Среднее ближайших меньшего и большего элементов
+    Midpoint,
+    /// Тип 7, Excel КВАРТИЛЬ.ВКЛ
+    #[default]
+    Linear,
+    /// Тип 6, Excel КВАРТИЛЬ.ИСКЛ
+    Weibull,
+    /// Тип 5
+    Hazen,
+    /// Тип 8, не зависит от распределения
+    MedianUnbiased,
+}
+
+impl std::fmt::Display for QuantileMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+impl QuantileMethod {
+    /// Квантиль `p` (0..=1) отсортированной непустой выборки `sorted`
+    pub fn quantile(&self, sorted: &[f64], p: f64) -> f64 {
+        let n = sorted.len();
+        let last = (n - 1) as f64;
+
+        // Позиция квантиля в выборке, индексы с 0
+        let h = match self {
+            Self::Lower | Self::Higher | Self::Nearest | Self::Midpoint | Self::Linear => last * p,
+            Self::Weibull => (n as f64 + 1.0) * p - 1.0,
+            Self::Hazen => n as f64 * p - 0.5,
+            Self::MedianUnbiased => (n as f64 + 1.0 / 3.0) * p - 2.0 / 3.0,
+        }
+        .clamp(0.0, last);
+
+        let lo = sorted[h.floor() as usize];
+        let hi = sorted[h.ceil() as usize];
+        match self {
+            Self::Lower => lo,
+            Self::Higher => hi,
+            Self::Nearest => sorted[h.round_ties_even() as usize],
+            Self::Midpoint => (lo + hi) / 2.0,
+            _ => lo + (h - h.floor()) * (hi - lo),
+        }
+    }
+}
+
+/// Способ отбраковки выбросов
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum OutlierMethod {
+    /// Без отбраковки
+    #[default]
+    None,
+    /// Вне границ Тьюки: `q1 - k * iqr`, `q3 + k * iqr`
+    Tukey,
+    /// Дальше `k * MAD` от медианы, MAD приведена к СКО нормального распределения
+    Mad,
+}
+
+impl std::fmt::Display for OutlierMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Отбраковка выбросов способом `method` с множителем `multiplier`
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct OutlierRejection {
+    pub method: OutlierMethod,
+    pub multiplier: f64,
+}
+
+impl Default for OutlierRejection {
+    fn default() -> Self {
+        Self {
+            method: OutlierMethod::default(),
+            multiplier: 3.0,
+        }
+    }
+}
+
+impl OutlierRejection {
+    /// Коэффициент перехода от MAD к СКО для нормального распределения
+    pub const MAD_SCALE: f64 = 1.4826;
+
+    /// Границы, вне которых отсчет считается выбросом.
+    /// `None` - отбраковка отключена или в выборке нет чисел
+    pub fn bounds<T: Float>(
+        &self,
+        series: &[T],
+        quantile_method: QuantileMethod,
+    ) -> Option<(T, T)> {
+        let sorted = sorted(series);
+        if sorted.is_empty() {
+            return None;
+        }
+
+        let k = self.multiplier;
+        let (lower, upper) = match self.method {
+            OutlierMethod::None => return None,
+            OutlierMethod::Tukey => {
+                let q1 = quantile_method.quantile(&sorted, 0.25);
+                let q3 = quantile_method.quantile(&sorted, 0.75);
+                (q1 - k * (q3 - q1), q3 + k * (q3 - q1))
+            }
+            OutlierMethod::Mad => {
+                let median = quantile_method.quantile(&sorted, 0.5);
+                let mut deviations = sorted
+                    .iter()
+                    .map(|v| (v - median).abs())
+                    .collect::<Vec<_>>();
+                deviations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let mad = Self::MAD_SCALE * quantile_method.quantile(&deviations, 0.5);
+                (median - k * mad, median + k * mad)
+            }
+        };
+
+        Some((T::from(lower)?, T::from(upper)?))
+    }
+
+    /// Разделить выборку на очищенную от выбросов и индексы выбросов в исходной выборке
+    pub fn reject<T: Float>(
+        &self,
+        series: &[T],
+        quantile_method: QuantileMethod,
+    ) -> (Vec<T>, Vec<usize>) {
+        match self.bounds(series, quantile_method) {
+            Some((lower, upper)) => {
+                let mut cleaned = Vec::with_capacity(series.len());
+                let mut rejected = Vec::new();
+                for (i, &v) in series.iter().enumerate() {
+                    if v < lower || v > upper {
+                        rejected.push(i);
+                    } else {
+                        cleaned.push(v);
+                    }
+                }
+                (cleaned, rejected)
+            }
+            None => (series.to_vec(), Vec::new()),
+        }
+    }
+}
+
+/// Отсортированные значения выборки без NaN
+fn sorted<T: Float>(series: &[T]) -> Vec<f64> {
+    let mut sorted = series
+        .iter()
+        .filter(|v| !v.is_nan())
+        .filter_map(|v| v.to_f64())
+        .collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted
+}
+
+/// Параметры вычисления статистики
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct StatisticsOptions {
+    /// Способ вычисления квантилей
+    pub quantile_method: QuantileMethod,
+    /// Множитель межквартильного размаха для границ выбросов (правило Тьюки)
+    pub fence: f64,
+    /// Доверительная вероятность интервала для среднего
+    pub confidence: f64,
+    /// Отбраковка выбросов перед вычислением результата измерения
+    pub outliers: OutlierRejection,
+}
+
+impl Default for StatisticsOptions {
+    fn default() -> Self {
+        Self {
+            quantile_method: QuantileMethod::default(),
+            fence: 1.5,
+            confidence: 0.95,
+            outliers: OutlierRejection::default(),
+        }
+    }
+}
+
+/// Описательная статистика выборки, значения NaN не учитываются.
+/// Для пустой выборки все величины, кроме `count`, - NaN
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Statistics<T: Serialize> {
+    count: usize,
+    min: T,
+    max: T,
+    mean: T,
+    std_dev: T,
+    std_err: T,
+    median: T,
+    q1: T,
+    q3: T,
+    iqr: T,
+    fence: T,
+    lower_bound: T,
+    upper_bound: T,
+    confidence: T,
+    ci_lower: T,
+    ci_upper: T,
+}
+
+impl<T> Statistics<T>
+where
+    T: Float + FromPrimitive + Serialize,
+{
+    /// Статистика с параметрами по умолчанию
+    pub fn new(series: &[T]) -> Self {
+        Self::with_options(series, &StatisticsOptions::default())
+    }
+
+    pub fn with_options(series: &[T], options: &StatisticsOptions) -> Self {
+        let sorted = sorted(series);
+
+        let count = sorted.len();
+        let n = count as f64;
+
+        let (min, max, median, q1, q3) = if sorted.is_empty() {
+            (f64::NAN, f64::NAN, f64::NAN, f64::NAN, f64::NAN)
+        } else {
+            let q = |p| options.quantile_method.quantile(&sorted, p);
+            (sorted[0], sorted[count - 1], q(0.5), q(0.25), q(0.75))
+        };
+
+        let mean = sorted.iter().sum::<f64>() / n;
+        // несмещенная оценка, для одного отсчета - NaN
+        let std_dev = (sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+        let std_err = std_dev / n.sqrt();
+        let half_width = if count > 1 {
+            student_t_quantile(options.confidence, count - 1) * std_err
+        } else {
+            f64::NAN
+        };
+
+        let iqr = q3 - q1;
+        let t = |v: f64| T::from_f64(v).unwrap_or_else(T::nan);
+
+        Self {
+            count,
+            min: t(min),
+            max: t(max),
+            mean: t(mean),
+            std_dev: t(std_dev),
+            std_err: t(std_err),
+            median: t(median),
+            q1: t(q1),
+            q3: t(q3),
+            iqr: t(iqr),
+            fence: t(options.fence),
+            lower_bound: t(q1 - options.fence * iqr),
+            upper_bound: t(q3 + options.fence * iqr),
+            confidence: t(options.confidence),
+            ci_lower: t(mean - half_width),
+            ci_upper: t(mean + half_width),
+        }
+    }
+
+    /// Число учтенных отсчетов
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn min(&self) -> T {
+        self.min
+    }
+
+    pub fn max(&self) -> T {
+        self.max
+    }
+
+    pub fn mean(&self) -> T {
+        self.mean
+    }
+
+    /// Выборочное СКО
+    pub fn std_dev(&self) -> T {
+        self.std_dev
+    }
+
+    /// Стандартная ошибка среднего
+    pub fn std_err(&self) -> T {
+        self.std_err
+    }
+
+    pub fn median(&self) -> T {
+        self.median
+    }
+
+    pub fn q1(&self) -> T {
+        self.q1
+    }
+
+    pub fn q3(&self) -> T {
+        self.q3
+    }
+
+    pub fn iqr(&self) -> T {
+        self.iqr
+    }
+
+    /// Множитель межквартильного размаха для границ выбросов
+    pub fn fence(&self) -> T {
+        self.fence
+    }
+
+    /// Нижняя граница выбросов: `q1 - fence * iqr`
+    pub fn lower_bound(&self) -> T {
+        self.lower_bound
+    }
+
+    /// Верхняя граница выбросов: `q3 + fence * iqr`
+    pub fn upper_bound(&self) -> T {
+        self.upper_bound
+    }
+
+    /// Доверительная вероятность интервала для среднего
+    pub fn confidence(&self) -> T {
+        self.confidence
+    }
+
+    /// Доверительный интервал для среднего по распределению Стьюдента
+    pub fn confidence_interval(&self) -> (T, T) {
+        (self.ci_lower, self.ci_upper)
+    }
+
+    /// Значение выходит за границы выбросов
+    pub fn is_outlier(&self, value: T) -> bool {
+        value < self.lower_bound || value > self.upper_bound
+    }
+}
+
+/// P(|T| < t) для распределения Стьюдента с `df` степенями свободы
+/// (Abramowitz & Stegun, 26.7.3 и 26.7.4)
+fn student_t_central(t: f64, df: usize) -> f64 {
+    let theta = (t / (df as f64).sqrt()).atan();
+    let (sin, cos) = theta.sin_cos();
+    let cos2 = cos * cos;
+
+    if df % 2 == 1 {
+        let mut sum = 0.0;
+        if df > 1 {
+            let mut term = 1.0;
+            sum = 1.0;
+            for k in 1..=(df - 3) / 2 {
+                term *= cos2 * (2 * k) as f64 / (2 * k + 1) as f64;
+                sum += term;
+            }
+        }
+        std::f64::consts::FRAC_2_PI * (theta + sin * cos * sum)
+    } else {
+        let mut term = 1.0;
+        let mut sum = 1.0;
+        for k in 1..=(df - 2) / 2 {
+            term *= cos2 * (2 * k - 1) as f64 / (2 * k) as f64;
+            sum += term;
+        }
+        sin * sum
+    }
+}
+
+/// Двусторонний критический коэффициент Стьюдента: P(|T| < t) = `confidence`
+pub fn student_t_quantile(confidence: f64, df: usize) -> f64 {
+    if df == 0 || !(0.0..1.0).contains(&confidence) {
+        return f64::NAN;
+    }
+
+    let mut hi = 1.0;
+    while student_t_central(hi, df) < confidence {
+        hi *= 2.0;
+    }
+
+    let mut lo = 0.0;
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2.0;
+        if student_t_central(mid, df) < confidence {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
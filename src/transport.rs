@@ -0,0 +1,78 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+/// Префикс адреса устройства, подключенного по сети (ser2net и т.п.)
+pub const TCP_PREFIX: &str = "tcp://";
+
+/// Скорость последовательного порта устройства
+const BAUD_RATE: u32 = 1500000;
+
+/// Канал связи с устройством: последовательный порт или TCP соединение
+pub enum Transport {
+    Serial(SerialStream),
+    Tcp(TcpStream),
+}
+
+impl Transport {
+    /// Открыть канал связи по адресу `port`:
+    /// `tcp://host:port` - TCP соединение, иначе - имя последовательного порта
+    pub async fn open(port: &str) -> io::Result<Self> {
+        if let Some(addr) = port.strip_prefix(TCP_PREFIX) {
+            let stream = TcpStream::connect(addr).await?;
+            stream.set_nodelay(true)?;
+            Ok(Self::Tcp(stream))
+        } else {
+            let stream = tokio_serial::new(port, BAUD_RATE).open_native_async()?;
+            Ok(Self::Serial(stream))
+        }
+    }
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Serial(s) => Pin::new(s).poll_read(cx, buf),
+            Self::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Serial(s) => Pin::new(s).poll_write(cx, buf),
+            Self::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Serial(s) => Pin::new(s).poll_flush(cx),
+            Self::Tcp(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Serial(s) => Pin::new(s).poll_shutdown(cx),
+            Self::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
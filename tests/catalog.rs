@@ -0,0 +1,169 @@
+use clicker_data_collector::{
+    data_model::{DataModel, ResonatorData},
+    FreqTolerance, MeasureProcessStat, ResonatorCatalog, ResonatorType,
+};
+
+fn resonator_type() -> ResonatorType {
+    ResonatorType {
+        name: "РК-32".to_string(),
+        nominal_freq: 32768.0,
+        freq_tolerance: FreqTolerance::Ppm(20.0),
+        max_rk: 70.0,
+        max_freq_iqr: 0.5,
+        max_rk_iqr: None,
+    }
+}
+
+fn resonator(freqs: &[f32], rks: &[f32]) -> ResonatorData {
+    MeasureProcessStat::from((freqs, rks)).try_into().unwrap()
+}
+
+#[test]
+fn tolerance_in_hz() {
+    assert!((FreqTolerance::Ppm(20.0).hz(32768.0) - 0.655_36).abs() < 1e-5);
+    assert_eq!(FreqTolerance::Hz(1.5).hz(32768.0), 1.5);
+}
+
+#[test]
+fn passed_within_limits() {
+    let verdict = resonator_type().evaluate(&resonator(&[32768.1, 32768.2, 32768.3], &[50.0]));
+    assert!(verdict.passed, "{verdict}");
+    assert!(verdict.reasons.is_empty());
+}
+
+#[test]
+fn failed_with_reasons() {
+    let t = resonator_type();
+
+    // 32769 Гц - 30 ppm от номинала
+    let verdict = t.evaluate(&resonator(&[32769.0], &[50.0]));
+    assert!(!verdict.passed);
+    assert_eq!(verdict.reasons.len(), 1);
+    assert!(verdict.reasons[0].starts_with("F "), "{verdict}");
+
+    let verdict = t.evaluate(&resonator(&[32768.0], &[80.0]));
+    assert!(!verdict.passed);
+    assert!(verdict.reasons[0].starts_with("Rk "), "{verdict}");
+
+    // IQR частоты 1 Гц
+    let verdict = t.evaluate(&resonator(
+        &[32767.0, 32767.5, 32768.0, 32768.5, 32769.0],
+        &[50.0],
+    ));
+    assert!(!verdict.passed);
+    assert!(
+        verdict.reasons.iter().any(|r| r.starts_with("Разброс F")),
+        "{verdict}"
+    );
+
+    let verdict = ResonatorType {
+        max_rk_iqr: Some(1.0),
+        ..t.clone()
+    }
+    .evaluate(&resonator(&[32768.0], &[40.0, 45.0, 50.0, 55.0, 60.0]));
+    assert!(!verdict.passed);
+    assert!(
+        verdict.reasons.iter().any(|r| r.starts_with("Разброс Rk")),
+        "{verdict}"
+    );
+
+    // нет данных - не годен
+    let verdict = t.evaluate(&resonator(&[f32::NAN], &[f32::NAN]));
+    assert!(!verdict.passed);
+    assert_eq!(verdict.reasons.len(), 3, "{verdict}");
+}
+
+#[test]
+fn validate_limits() {
+    assert!(resonator_type().validate().is_ok());
+    assert!(ResonatorType {
+        name: " ".to_string(),
+        ..resonator_type()
+    }
+    .validate()
+    .is_err());
+    assert!(ResonatorType {
+        freq_tolerance: FreqTolerance::Hz(0.0),
+        ..resonator_type()
+    }
+    .validate()
+    .is_err());
+    assert!(ResonatorType {
+        max_rk_iqr: Some(f32::NAN),
+        ..resonator_type()
+    }
+    .validate()
+    .is_err());
+}
+
+#[test]
+fn catalog_insert_replace_remove() {
+    let mut catalog = ResonatorCatalog::default();
+    catalog.insert(resonator_type());
+    catalog.insert(ResonatorType {
+        name: "РК-16".to_string(),
+        nominal_freq: 16384.0,
+        ..resonator_type()
+    });
+    assert_eq!(catalog.types().len(), 2);
+
+    catalog.insert(ResonatorType {
+        max_rk: 100.0,
+        ..resonator_type()
+    });
+    assert_eq!(catalog.types().len(), 2);
+    assert_eq!(catalog.get("РК-32").unwrap().max_rk, 100.0);
+
+    assert!(catalog.remove("РК-32").is_some());
+    assert!(catalog.remove("РК-32").is_none());
+    assert!(catalog.get("РК-32").is_none());
+    assert_eq!(catalog.types().len(), 1);
+}
+
+#[test]
+fn catalog_persistence() {
+    let dir = std::env::temp_dir().join(format!("clicker-catalog-{}", std::process::id()));
+    let path = dir.join("resonator_types.json");
+
+    assert_eq!(
+        ResonatorCatalog::load(&path).unwrap(),
+        ResonatorCatalog::default()
+    );
+
+    let mut catalog = ResonatorCatalog::default();
+    catalog.insert(resonator_type());
+    catalog.save(&path).unwrap();
+    assert_eq!(ResonatorCatalog::load(&path).unwrap(), catalog);
+
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(json[0]["Name"], "РК-32");
+    assert_eq!(json[0]["FreqTolerance"]["Ppm"], 20.0);
+
+    std::fs::write(&path, "not a json").unwrap();
+    assert!(ResonatorCatalog::load(&path).is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn data_model_evaluated_by_batch_type() {
+    let mut catalog = ResonatorCatalog::default();
+    catalog.insert(resonator_type());
+
+    let mut data_model = DataModel::default();
+    data_model.resonators.push(resonator(&[32768.0], &[50.0]));
+    data_model.resonators.push(resonator(&[32770.0], &[50.0]));
+
+    data_model.evaluate(&catalog);
+    assert!(data_model.resonators.iter().all(|r| r.verdict.is_none()));
+
+    data_model.data_type = "РК-32".to_string();
+    data_model.evaluate(&catalog);
+    let verdicts = data_model
+        .resonators
+        .iter()
+        .map(|r| r.verdict.as_ref().unwrap().passed)
+        .collect::<Vec<_>>();
+    assert_eq!(verdicts, vec![true, false]);
+}
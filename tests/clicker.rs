@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+};
+use tokio_util::codec::{Decoder, Framed};
+
+use clicker_data_collector::{
+    Capture, Clicker, ClickerInterface, DeviceEmulator, EmulationProfile, ProtobufMDCodec,
+    ProtobufMDDeviceCodec, ReplayClicker, TCP_PREFIX,
+};
+
+type Response = <ProtobufMDCodec as Decoder>::Item;
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+/// Ошибка протокола при неверном ответе устройства
+const PROTOCOL_ERROR: &str = "Err(Protocol(ProtocolError))";
+
+/// Отвечать на каждый запрос, пришедший через `io`, ответом из `responses`
+async fn serve<IO: AsyncRead + AsyncWrite + Unpin>(io: IO, responses: Vec<Response>) {
+    let mut io = Framed::new(io, ProtobufMDDeviceCodec::default());
+    for mut resp in responses {
+        let Some(Ok(req)) = io.next().await else {
+            return;
+        };
+        resp.id = req.id;
+        resp.device_id = req.device_id;
+        resp.protocol_version = req.protocol_version;
+        io.send(resp).await.unwrap();
+    }
+}
+
+/// Устройство, отвечающее на каждый запрос ответом из `responses`
+fn device(responses: Vec<Response>) -> Clicker<tokio::io::DuplexStream> {
+    let (host, dev) = tokio::io::duplex(1024);
+    tokio::spawn(serve(dev, responses));
+    Clicker::from_io(host, TIMEOUT, 0)
+}
+
+/// Ответ со статусом `global_status` (по умолчанию - Ok), `with_output` - с пустыми выходными значениями
+fn response(global_status: Option<i32>, with_output: bool) -> Response {
+    Response {
+        global_status: global_status.unwrap_or_default(),
+        output: with_output.then(Default::default),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn malformed_responses_are_errors() {
+    let mut clicker = device(vec![
+        response(None, false),
+        response(None, true),
+        response(Some(12345), false),
+    ]);
+
+    for _ in 0..3 {
+        assert_eq!(format!("{:?}", clicker.read().await), PROTOCOL_ERROR);
+    }
+}
+
+#[tokio::test]
+async fn unknown_status_fails_test() {
+    let mut clicker = device(vec![response(Some(12345), false)]);
+    assert_eq!(format!("{:?}", clicker.test().await), PROTOCOL_ERROR);
+}
+
+#[tokio::test]
+async fn tcp_address_connects_to_listener() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = format!("{TCP_PREFIX}{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        serve(
+            stream,
+            vec![response(None, false), response(Some(12345), false)],
+        )
+        .await;
+    });
+
+    // подключение проверяет связь первым запросом
+    let mut clicker = Clicker::connect(port.clone(), TIMEOUT, 0)
+        .await
+        .expect("device on tcp address not connected");
+    assert_eq!(format!("{:?}", clicker.test().await), PROTOCOL_ERROR);
+
+    // слушатель принимал одно подключение и закрыт
+    drop(clicker);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(Clicker::new(&port, TIMEOUT, 0).await.is_err());
+    // адрес без порта
+    assert!(Clicker::new(&format!("{TCP_PREFIX}127.0.0.1"), TIMEOUT, 0)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn capture_replays_measurements() {
+    const READS: usize = 20;
+
+    let path = std::env::temp_dir().join(format!("clicker-capture-{}.jsonl", std::process::id()));
+
+    let (host, dev) = tokio::io::duplex(1024);
+    tokio::spawn(async move {
+        DeviceEmulator::new(EmulationProfile {
+            error_probability: 0.2,
+            seed: Some(42),
+            ..Default::default()
+        })
+        .serve(dev)
+        .await
+    });
+
+    // проверка связи записывается в захват так же, как при подключении сервером
+    let mut clicker =
+        Clicker::from_io(host, TIMEOUT, 0).with_capture(Some(Capture::create(&path).unwrap()));
+    clicker.test().await.unwrap();
+    let mut recorded = Vec::new();
+    for _ in 0..READS {
+        recorded.push(format!("{:?}", clicker.read().await));
+    }
+    assert!(recorded.iter().any(|r| r.starts_with("Ok")));
+    assert!(recorded.iter().any(|r| r.starts_with("Err")));
+
+    let mut replay = ReplayClicker::open(&path, 0.0).unwrap();
+    let mut replayed = Vec::new();
+    for _ in 0..READS {
+        replayed.push(format!("{:?}", replay.read().await));
+    }
+    assert_eq!(replayed, recorded);
+    assert_eq!(
+        format!("{:?}", replay.read().await),
+        "Err(UnexpectedEndOfStream)"
+    );
+
+    std::fs::remove_file(&path).ok();
+}
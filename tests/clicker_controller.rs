@@ -0,0 +1,1048 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use futures::StreamExt;
+use tokio::sync::{broadcast, watch::Receiver};
+
+use clicker_data_collector::{
+    data_model::ResonatorData, AutoMeasureState, ClickerController, ClickerInterface,
+    ConnectionState, ControllerEvent, EmulationProfile, FakeClicker, FixedDuration, InjectedError,
+    InterruptReason, IqrThreshold, MeasureProcessStat, MeasureProcessState, MeasureResult,
+    MeasureTimeout, MeasureTimeouts, OutlierMethod, OutlierRejection, PresenceDetector,
+    QuantileMethod, SampleCount, SampleStatus, SegmentMode, Settling, Statistics,
+    StatisticsOptions, SwitchCycles,
+};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+const SWITCH_CYCLES: u32 = 2;
+const SEED: u64 = 42;
+
+fn profile() -> EmulationProfile {
+    EmulationProfile {
+        switch_period_ms: 1000,
+        seed: Some(SEED),
+        ..Default::default()
+    }
+}
+
+/// Контроллер с подключенным эмулятором, дождавшийся подключения
+async fn connected_controller(profile: EmulationProfile) -> ClickerController {
+    let mut ctrl = ClickerController::new(UPDATE_INTERVAL, SWITCH_CYCLES);
+    ctrl.attach(move || {
+        let profile = profile.clone();
+        async move { Ok::<_, InjectedError>(FakeClicker::from_profile(profile)) }
+    });
+
+    ctrl.subscribe_connection_state()
+        .wait_for(|s| *s == ConnectionState::Connected)
+        .await
+        .expect("connection state channel closed");
+    ctrl
+}
+
+/// Дождаться завершения измерительного процесса
+async fn wait_done(mut rx: Receiver<MeasureProcessStat>) -> MeasureProcessStat {
+    tokio::time::timeout(
+        Duration::from_secs(60),
+        rx.wait_for(|s| {
+            !matches!(
+                s.state,
+                MeasureProcessState::Idle | MeasureProcessState::Running
+            )
+        }),
+    )
+    .await
+    .expect("measure did not finish in time")
+    .expect("measure status channel closed")
+    .clone()
+}
+
+/// Получать события контроллера до завершения или прерывания измерения
+async fn collect_events(rx: &mut broadcast::Receiver<ControllerEvent>) -> Vec<ControllerEvent> {
+    let mut events = Vec::new();
+    loop {
+        let event = tokio::time::timeout(Duration::from_secs(60), rx.recv())
+            .await
+            .expect("no measure end event in time")
+            .expect("events channel failed");
+        let done = matches!(
+            event,
+            ControllerEvent::MeasureFinished | ControllerEvent::MeasureInterrupted { .. }
+        );
+        events.push(event);
+        if done {
+            return events;
+        }
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn seeded_fake_clicker_is_reproducible() {
+    let mut a = FakeClicker::from_profile(profile());
+    let mut b = FakeClicker::from_profile(profile());
+
+    for _ in 0..50 {
+        let (ra, rb) = (a.read().await, b.read().await);
+        assert_eq!(format!("{ra:?}"), format!("{rb:?}"));
+        tokio::time::advance(UPDATE_INTERVAL).await;
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn fake_clicker_switches_mode_by_time() {
+    let mut clicker = FakeClicker::from_profile(profile());
+
+    let mut modes = String::new();
+    for _ in 0..4 {
+        let res = clicker.read().await.unwrap();
+        modes.push_str(&format!("{res:?}")[..1]);
+        tokio::time::advance(Duration::from_millis(1000)).await;
+    }
+    assert_eq!(modes, "RFRF");
+}
+
+#[tokio::test(start_paused = true)]
+async fn start_without_device_fails() {
+    let mut ctrl = ClickerController::new(UPDATE_INTERVAL, SWITCH_CYCLES);
+    assert!(ctrl.start_mesure().is_err());
+    assert!(ctrl.subscribe_measure_status().is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_finishes_after_switch_cycles() {
+    let p = profile();
+    let mut ctrl = connected_controller(p.clone()).await;
+
+    ctrl.start_mesure().unwrap();
+    assert!(
+        ctrl.start_mesure().is_err(),
+        "second start must be rejected"
+    );
+
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+
+    // Первый неполный период отбрасывается, затем SWITCH_CYCLES полных циклов Rk -> F
+    let per_mode = (p.switch_period_ms / UPDATE_INTERVAL.as_millis() as u64) as usize;
+    let expected = per_mode * SWITCH_CYCLES as usize;
+    assert!(stat.rks.len() >= expected, "rks: {}", stat.rks.len());
+    assert!(stat.freqs.len() >= expected, "freqs: {}", stat.freqs.len());
+
+    let freq = stat.freqs_avg.expect("no freq statistics").median();
+    let rk = stat.rks_avg.expect("no rk statistics").median();
+    assert!(
+        (freq - p.nominal_freq).abs() < 6.0 * p.freq_spread,
+        "freq: {freq}"
+    );
+    assert!((rk - p.nominal_rk).abs() < 6.0 * p.rk_spread, "rk: {rk}");
+
+    // после завершения можно запустить новое измерение
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_is_reproducible() {
+    let mut results = vec![];
+    for _ in 0..2 {
+        let mut ctrl = connected_controller(profile()).await;
+        ctrl.start_mesure().unwrap();
+        let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+        results.push((stat.freqs, stat.rks));
+    }
+    assert_eq!(results[0], results[1]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_canceled() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    ctrl.start_mesure().unwrap();
+    let rx = ctrl.subscribe_measure_status().unwrap();
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    ctrl.interrupt_mesure().await;
+    assert!(ctrl.subscribe_measure_status().is_none());
+
+    let stat = wait_done(rx).await;
+    assert_eq!(
+        stat.state,
+        MeasureProcessState::Interrupted(InterruptReason::Canceled)
+    );
+
+    // отмена не мешает следующему измерению
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+}
+
+#[tokio::test(start_paused = true)]
+async fn cancel_subscription_ignores_past_cancels() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    ctrl.start_mesure().unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    ctrl.interrupt_mesure().await;
+
+    // подписка после отмены не видит ее, следующее измерение серии запускается и завершается
+    let cancel_rx = ctrl.subscribe_cancel();
+    assert!(!cancel_rx.has_changed().unwrap());
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+    assert!(!cancel_rx.has_changed().unwrap());
+
+    // новая отмена подписке видна
+    ctrl.interrupt_mesure().await;
+    assert!(cancel_rx.has_changed().unwrap());
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_interrupted_by_detach() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    ctrl.start_mesure().unwrap();
+    let rx = ctrl.subscribe_measure_status().unwrap();
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    ctrl.detach();
+
+    let stat = wait_done(rx).await;
+    assert!(
+        matches!(
+            stat.state,
+            MeasureProcessState::Interrupted(InterruptReason::DeviceError(_))
+        ),
+        "{:?}",
+        stat.state
+    );
+    assert!(ctrl.start_mesure().is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_interrupted_by_read_errors() {
+    let mut ctrl = connected_controller(EmulationProfile {
+        error_probability: 1.0,
+        ..profile()
+    })
+    .await;
+
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert!(
+        matches!(
+            stat.state,
+            MeasureProcessState::Interrupted(InterruptReason::DeviceError(_))
+        ),
+        "{:?}",
+        stat.state
+    );
+    assert!(stat.freqs.is_empty() && stat.rks.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn sample_count_strategy() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    ctrl.start_mesure_with(
+        Box::new(SampleCount::new(7)),
+        Settling::default(),
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+    assert_eq!(stat.freqs.len(), 7);
+    assert_eq!(stat.rks.len(), 7);
+}
+
+#[tokio::test(start_paused = true)]
+async fn fixed_duration_strategy() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    let start = tokio::time::Instant::now();
+    ctrl.start_mesure_with(
+        Box::new(FixedDuration::new(Duration::from_millis(2500))),
+        Settling::default(),
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    let elapsed = start.elapsed();
+
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+    assert!(elapsed >= Duration::from_millis(2500), "{elapsed:?}");
+    assert!(
+        elapsed < Duration::from_millis(2500) + 2 * UPDATE_INTERVAL,
+        "{elapsed:?}"
+    );
+    assert!(!stat.freqs.is_empty() && !stat.rks.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn short_fixed_duration_waits_for_both_modes() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    // короче периода переключения режима
+    let duration = Duration::from_millis(profile().switch_period_ms / 4);
+    ctrl.start_mesure_with(
+        Box::new(FixedDuration::new(duration)),
+        Settling::default(),
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+    assert!(ResonatorData::try_from(stat).is_ok());
+}
+
+#[tokio::test(start_paused = true)]
+async fn iqr_strategy_stops_when_stable() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    let start = tokio::time::Instant::now();
+    let strategy = IqrThreshold::new(1.0, 10.0, Duration::from_secs(30));
+    ctrl.start_mesure_with(
+        Box::new(strategy),
+        Settling::default(),
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+    assert!(start.elapsed() < Duration::from_secs(30));
+    assert!(stat.freqs.len() >= IqrThreshold::MIN_SAMPLES);
+    assert!(stat.rks.len() >= IqrThreshold::MIN_SAMPLES);
+}
+
+#[tokio::test(start_paused = true)]
+async fn iqr_strategy_limited_by_max_time() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    let start = tokio::time::Instant::now();
+    let strategy = IqrThreshold::new(1e-6, 1e-6, Duration::from_secs(5));
+    ctrl.start_mesure_with(
+        Box::new(strategy),
+        Settling::default(),
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+    assert!(start.elapsed() >= Duration::from_secs(5));
+}
+
+#[tokio::test(start_paused = true)]
+async fn settling_samples_discarded_after_switch() {
+    const SETTLING: u32 = 3;
+
+    let mut ctrl = connected_controller(profile()).await;
+
+    let settling = Settling::new(SETTLING, Duration::ZERO);
+    ctrl.start_mesure_with(
+        Box::new(SwitchCycles::new(SWITCH_CYCLES)),
+        settling,
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+
+    // после каждого из SWITCH_CYCLES * 2 + 1 переключений
+    let switches = SWITCH_CYCLES as usize * 2 + 1;
+    assert_eq!(
+        stat.discarded_freqs.len() + stat.discarded_rks.len(),
+        switches * SETTLING as usize
+    );
+    assert!(stat.discarded_rks.len() >= SWITCH_CYCLES as usize * SETTLING as usize);
+    assert!(stat.discarded_freqs.len() >= SWITCH_CYCLES as usize * SETTLING as usize);
+
+    let per_mode = (profile().switch_period_ms / UPDATE_INTERVAL.as_millis() as u64) as usize;
+    let expected = (per_mode - SETTLING as usize) * SWITCH_CYCLES as usize;
+    assert!(stat.rks.len() >= expected, "rks: {}", stat.rks.len());
+    assert!(stat.freqs.len() >= expected, "freqs: {}", stat.freqs.len());
+}
+
+#[tokio::test(start_paused = true)]
+async fn settling_time_discarded_after_switch() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    let settling = Settling::new(0, Duration::from_millis(450));
+    ctrl.start_mesure_with(
+        Box::new(SampleCount::new(10)),
+        settling,
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+
+    // 0, 100, 200, 300, 400 мс после переключения
+    assert_eq!(stat.discarded_freqs.len() % 5, 0);
+    assert_eq!(stat.discarded_rks.len() % 5, 0);
+    assert!(!stat.discarded_freqs.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn samples_split_into_segments() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+
+    // Rk и F чередуются, последняя фаза - отсчет, завершивший измерение, она не полная
+    let modes = stat.segments.iter().map(|s| s.mode).collect::<Vec<_>>();
+    assert!(modes.windows(2).all(|w| w[0] != w[1]), "{modes:?}");
+    let (last, complete) = stat.segments.split_last().unwrap();
+    assert_eq!(complete.len(), SWITCH_CYCLES as usize * 2, "{modes:?}");
+    assert!(complete.iter().all(|s| s.complete));
+    assert!(!last.complete);
+    assert_eq!(last.values.len(), 1);
+
+    let values = |mode| {
+        stat.segments
+            .iter()
+            .filter(|s| s.mode == mode)
+            .flat_map(|s| s.values.iter().copied())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(values(SegmentMode::Freq), stat.freqs);
+    assert_eq!(values(SegmentMode::Rk), stat.rks);
+
+    for segment in &stat.segments {
+        assert_eq!(
+            segment.stat.median(),
+            Statistics::new(&segment.values).median()
+        );
+    }
+
+    let freq = stat.freqs_repeatability.expect("no freq repeatability");
+    let rk = stat.rks_repeatability.expect("no rk repeatability");
+    assert_eq!(freq.cycles, SWITCH_CYCLES as usize);
+    assert_eq!(rk.cycles, SWITCH_CYCLES as usize);
+    // резонатор не меняется, медианы отличаются только шумом
+    assert!(freq.range < 6.0 * profile().freq_noise, "{freq:?}");
+    assert!(rk.range < 6.0 * profile().rk_noise, "{rk:?}");
+}
+
+#[tokio::test(start_paused = true)]
+async fn samples_timestamped_in_order() {
+    const SETTLING: u32 = 2;
+
+    let mut ctrl = connected_controller(profile()).await;
+
+    let settling = Settling::new(SETTLING, Duration::ZERO);
+    ctrl.start_mesure_with(
+        Box::new(SwitchCycles::new(SWITCH_CYCLES)),
+        settling,
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+
+    // отсчеты идут с периодом опроса по монотонным часам
+    let step = UPDATE_INTERVAL.as_millis() as f64;
+    assert!(stat
+        .samples
+        .windows(2)
+        .all(|w| w[1].offset_ms - w[0].offset_ms == step));
+    assert!(stat
+        .samples
+        .windows(2)
+        .all(|w| w[1].unix_ms >= w[0].unix_ms));
+    assert!(stat.samples.iter().all(|s| s.unix_ms > 0.0));
+
+    let count = |status, mode| {
+        stat.samples
+            .iter()
+            .filter(|s| s.status == status && s.mode == mode)
+            .count()
+    };
+    assert_eq!(
+        count(SampleStatus::Accepted, SegmentMode::Freq),
+        stat.freqs.len()
+    );
+    assert_eq!(
+        count(SampleStatus::Accepted, SegmentMode::Rk),
+        stat.rks.len()
+    );
+    assert_eq!(
+        count(SampleStatus::Settling, SegmentMode::Freq),
+        stat.discarded_freqs.len()
+    );
+    assert_eq!(
+        count(SampleStatus::Settling, SegmentMode::Rk),
+        stat.discarded_rks.len()
+    );
+    // до первого переключения отсчеты не нужны стратегии
+    assert_eq!(stat.samples[0].status, SampleStatus::Skipped);
+}
+
+#[tokio::test(start_paused = true)]
+async fn samples_attached_to_final_state() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    ctrl.start_mesure().unwrap();
+    let mut rx = ctrl.subscribe_measure_status().unwrap();
+    let mut running = 0;
+    let stat = loop {
+        rx.changed().await.unwrap();
+        let stat = rx.borrow_and_update().clone();
+        if stat.state != MeasureProcessState::Running {
+            break stat;
+        }
+        // пока измерение идет, передается только сводка
+        assert!(stat.samples.is_empty());
+        running += 1;
+    };
+
+    assert!(running > 0);
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+    assert_eq!(
+        stat.samples
+            .iter()
+            .filter(|s| matches!(s.status, SampleStatus::Accepted | SampleStatus::Rejected))
+            .count(),
+        stat.freqs.len() + stat.rks.len()
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn statistics_options_applied() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    let options = StatisticsOptions {
+        quantile_method: QuantileMethod::Lower,
+        fence: 3.0,
+        confidence: 0.99,
+        ..Default::default()
+    };
+    ctrl.start_mesure_with(Box::new(SampleCount::new(10)), Settling::default(), options)
+        .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+
+    let freqs = stat.freqs_avg.expect("no freq statistics");
+    assert_eq!(freqs.count(), stat.freqs.len());
+    assert_eq!(freqs.fence(), 3.0);
+    assert_eq!(freqs.confidence(), 0.99);
+    // способ Lower берет квартили из самих отсчетов
+    assert!(stat.freqs.contains(&freqs.q1()));
+    assert!(stat.freqs.contains(&freqs.q3()));
+    assert_eq!(freqs.lower_bound(), freqs.q1() - 3.0 * freqs.iqr());
+
+    for segment in &stat.segments {
+        assert_eq!(segment.stat.fence(), 3.0);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn outliers_rejected_from_result() {
+    let mut ctrl = connected_controller(EmulationProfile {
+        outlier_probability: 0.2,
+        outlier_scale: 50.0,
+        ..profile()
+    })
+    .await;
+
+    let options = StatisticsOptions {
+        outliers: OutlierRejection {
+            method: OutlierMethod::Tukey,
+            multiplier: 3.0,
+        },
+        ..Default::default()
+    };
+    ctrl.start_mesure_with(Box::new(SampleCount::new(30)), Settling::default(), options)
+        .unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+
+    assert!(!stat.rejected_freqs.is_empty());
+    assert!(!stat.rejected_rks.is_empty());
+
+    let freqs = stat.freqs_avg.unwrap();
+    let freqs_raw = stat.freqs_raw_avg.unwrap();
+    assert_eq!(freqs_raw.count(), stat.freqs.len());
+    assert_eq!(freqs.count(), stat.freqs.len() - stat.rejected_freqs.len());
+    assert!(freqs.max() - freqs.min() < freqs_raw.max() - freqs_raw.min());
+
+    let (lower, upper) = options
+        .outliers
+        .bounds(&stat.freqs, options.quantile_method)
+        .unwrap();
+    for &i in &stat.rejected_freqs {
+        assert!(stat.freqs[i] < lower || stat.freqs[i] > upper);
+    }
+
+    let rejected_samples = stat
+        .samples
+        .iter()
+        .filter(|s| s.status == SampleStatus::Rejected)
+        .count();
+    assert_eq!(
+        rejected_samples,
+        stat.rejected_freqs.len() + stat.rejected_rks.len()
+    );
+
+    let rks_avg = stat.rks_avg.unwrap();
+    let rks_raw_avg = stat.rks_raw_avg.unwrap();
+    let data = ResonatorData::try_from(stat.clone()).unwrap();
+    assert_eq!(data.frequency, freqs.median());
+    assert_eq!(data.raw_frequency, freqs_raw.median());
+    assert_eq!(data.rk, rks_avg.median());
+    assert_eq!(data.raw_rk, rks_raw_avg.median());
+    assert_eq!(data.rejected_freqs, stat.rejected_freqs);
+}
+
+/// Устройство с гнездом: без резонатора выдает NaN
+struct SocketClicker {
+    inserted: Arc<AtomicBool>,
+}
+
+impl ClickerInterface<InjectedError> for SocketClicker {
+    async fn read(&mut self) -> Result<MeasureResult, InjectedError> {
+        Ok(MeasureResult::Freq(
+            if self.inserted.load(Ordering::SeqCst) {
+                32768.0
+            } else {
+                f32::NAN
+            },
+        ))
+    }
+}
+
+/// Дождаться состояния автоматического запуска `state`
+async fn wait_auto(rx: &mut Receiver<AutoMeasureState>, state: AutoMeasureState) {
+    tokio::time::timeout(Duration::from_secs(10), rx.wait_for(|s| *s == state))
+        .await
+        .expect("auto measure state not reached in time")
+        .expect("auto measure state channel closed");
+}
+
+#[test]
+fn presence_detector_rejects_empty_socket_readings() {
+    let detector = PresenceDetector::new(1000.0, 100000.0, 100.0, 3);
+
+    assert!(detector.is_present(&MeasureResult::Freq(32768.0)));
+    assert!(detector.is_present(&MeasureResult::Rk(50.0)));
+
+    assert!(!detector.is_present(&MeasureResult::Freq(f32::NAN)));
+    assert!(!detector.is_present(&MeasureResult::Freq(0.0)));
+    assert!(!detector.is_present(&MeasureResult::Freq(500.0)));
+    assert!(!detector.is_present(&MeasureResult::Freq(200000.0)));
+    assert!(!detector.is_present(&MeasureResult::Rk(f32::NAN)));
+    assert!(!detector.is_present(&MeasureResult::Rk(0.0)));
+    assert!(!detector.is_present(&MeasureResult::Rk(150.0)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn auto_measure_starts_on_insertion() {
+    let inserted = Arc::new(AtomicBool::new(true));
+    let mut ctrl = ClickerController::new(UPDATE_INTERVAL, SWITCH_CYCLES);
+    {
+        let inserted = inserted.clone();
+        ctrl.attach(move || {
+            let inserted = inserted.clone();
+            async move { Ok::<_, InjectedError>(SocketClicker { inserted }) }
+        });
+    }
+    ctrl.subscribe_connection_state()
+        .wait_for(|s| *s == ConnectionState::Connected)
+        .await
+        .unwrap();
+
+    let measured = Arc::new(AtomicU32::new(0));
+    let counter = measured.clone();
+    ctrl.start_auto(
+        PresenceDetector::new(1000.0, 100000.0, 100.0, 3),
+        move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        },
+    )
+    .await;
+    let mut state = ctrl.subscribe_auto_state();
+
+    // Резонатор, установленный до включения режима, не измеряется
+    tokio::time::sleep(UPDATE_INTERVAL * 10).await;
+    assert_eq!(ctrl.auto_state(), AutoMeasureState::WaitingRemoval);
+    assert_eq!(measured.load(Ordering::SeqCst), 0);
+
+    for n in 1..=2 {
+        inserted.store(false, Ordering::SeqCst);
+        wait_auto(&mut state, AutoMeasureState::WaitingInsertion).await;
+
+        inserted.store(true, Ordering::SeqCst);
+        wait_auto(&mut state, AutoMeasureState::WaitingRemoval).await;
+        assert_eq!(measured.load(Ordering::SeqCst), n);
+
+        // Пока резонатор не снят, новое измерение не начинается
+        tokio::time::sleep(UPDATE_INTERVAL * 10).await;
+        assert_eq!(measured.load(Ordering::SeqCst), n);
+    }
+
+    ctrl.stop_auto().await;
+    assert_eq!(ctrl.auto_state(), AutoMeasureState::Disabled);
+}
+
+#[tokio::test(start_paused = true)]
+async fn stop_auto_cancels_running_measure() {
+    let inserted = Arc::new(AtomicBool::new(false));
+    let mut ctrl = ClickerController::new(UPDATE_INTERVAL, SWITCH_CYCLES);
+    {
+        let inserted = inserted.clone();
+        ctrl.attach(move || {
+            let inserted = inserted.clone();
+            async move { Ok::<_, InjectedError>(SocketClicker { inserted }) }
+        });
+    }
+    ctrl.subscribe_connection_state()
+        .wait_for(|s| *s == ConnectionState::Connected)
+        .await
+        .unwrap();
+    let mut state = ctrl.subscribe_auto_state();
+    let ctrl = Arc::new(tokio::sync::Mutex::new(ctrl));
+
+    // устройство не переключает режим, измерение не завершится само
+    let measure_rx = Arc::new(std::sync::Mutex::new(None));
+    {
+        let (c, slot) = (ctrl.clone(), measure_rx.clone());
+        ctrl.lock()
+            .await
+            .start_auto(
+                PresenceDetector::new(1000.0, 100000.0, 100.0, 3),
+                move || {
+                    let (c, slot) = (c.clone(), slot.clone());
+                    async move {
+                        let rx = {
+                            let mut guard = c.lock().await;
+                            guard.start_mesure().unwrap();
+                            guard.subscribe_measure_status().unwrap()
+                        };
+                        slot.lock().unwrap().replace(rx.clone());
+                        wait_done(rx).await;
+                    }
+                },
+            )
+            .await;
+    }
+
+    wait_auto(&mut state, AutoMeasureState::WaitingInsertion).await;
+    inserted.store(true, Ordering::SeqCst);
+    wait_auto(&mut state, AutoMeasureState::Measuring).await;
+    tokio::time::sleep(UPDATE_INTERVAL * 5).await;
+    let rx = measure_rx
+        .lock()
+        .unwrap()
+        .take()
+        .expect("measure not started");
+
+    ctrl.lock().await.stop_auto().await;
+    assert_eq!(ctrl.lock().await.auto_state(), AutoMeasureState::Disabled);
+
+    let stat = wait_done(rx).await;
+    assert_eq!(
+        stat.state,
+        MeasureProcessState::Interrupted(InterruptReason::Canceled)
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn live_readings_are_throttled() {
+    let ctrl = connected_controller(profile()).await;
+
+    let min_interval = UPDATE_INTERVAL * 5;
+    let start = tokio::time::Instant::now();
+    let readings = ctrl.live(min_interval).take(10).collect::<Vec<_>>().await;
+
+    assert_eq!(readings.len(), 10);
+    assert!(start.elapsed() >= min_interval * 9, "{:?}", start.elapsed());
+    assert!(readings.iter().any(|r| r.mode == SegmentMode::Freq));
+    assert!(readings.iter().any(|r| r.mode == SegmentMode::Rk));
+    assert!(readings.iter().all(|r| r.value.is_finite()));
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_lifecycle_events_published() {
+    const SETTLING: u32 = 3;
+
+    let mut ctrl = ClickerController::new(UPDATE_INTERVAL, SWITCH_CYCLES);
+    let mut events_rx = ctrl.subscribe_events();
+    ctrl.attach(|| async { Ok::<_, InjectedError>(FakeClicker::from_profile(profile())) });
+    ctrl.subscribe_connection_state()
+        .wait_for(|s| *s == ConnectionState::Connected)
+        .await
+        .unwrap();
+    assert_eq!(
+        events_rx.recv().await.unwrap(),
+        ControllerEvent::Reconnecting { attempt: 1 }
+    );
+    assert_eq!(events_rx.recv().await.unwrap(), ControllerEvent::Connected);
+
+    ctrl.start_mesure_with(
+        Box::new(SwitchCycles::new(SWITCH_CYCLES)),
+        Settling::new(SETTLING, Duration::ZERO),
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+    let events = collect_events(&mut events_rx).await;
+
+    assert_eq!(events.first(), Some(&ControllerEvent::MeasureStarted));
+    assert_eq!(events.last(), Some(&ControllerEvent::MeasureFinished));
+
+    let switches = events
+        .iter()
+        .filter(|e| matches!(e, ControllerEvent::ModeSwitched { .. }))
+        .count();
+    assert_eq!(switches, SWITCH_CYCLES as usize * 2 + 1);
+
+    // отброшенные отсчеты следуют за переключением в тот же режим
+    let mut mode = None;
+    let mut discarded = 0;
+    for event in &events {
+        match event {
+            ControllerEvent::ModeSwitched { mode: m } => mode = Some(*m),
+            ControllerEvent::SampleDiscarded { mode: m, value } => {
+                assert_eq!(Some(*m), mode);
+                assert!(!value.is_nan());
+                discarded += 1;
+            }
+            _ => (),
+        }
+    }
+    assert_eq!(discarded, switches * SETTLING as usize);
+
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(
+        stat.discarded_freqs.len() + stat.discarded_rks.len(),
+        discarded
+    );
+}
+
+/// Устройство, выдающее заданные отсчеты, после них - Rk
+struct ScriptClicker {
+    script: Arc<Mutex<VecDeque<MeasureResult>>>,
+}
+
+impl ClickerInterface<InjectedError> for ScriptClicker {
+    async fn read(&mut self) -> Result<MeasureResult, InjectedError> {
+        Ok(self
+            .script
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or(MeasureResult::Rk(50.0)))
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn switches_during_settling_counted() {
+    let script = Arc::new(Mutex::new(VecDeque::new()));
+    let mut ctrl = ClickerController::new(UPDATE_INTERVAL, SWITCH_CYCLES);
+    {
+        let script = script.clone();
+        ctrl.attach(move || {
+            let script = script.clone();
+            async move { Ok::<_, InjectedError>(ScriptClicker { script }) }
+        });
+    }
+    ctrl.subscribe_connection_state()
+        .wait_for(|s| *s == ConnectionState::Connected)
+        .await
+        .unwrap();
+    let mut events_rx = ctrl.subscribe_events();
+
+    ctrl.start_mesure_with(
+        Box::new(SwitchCycles::new(SWITCH_CYCLES)),
+        Settling::new(2, Duration::ZERO),
+        StatisticsOptions::default(),
+    )
+    .unwrap();
+
+    // Rk -> F -> Rk -> F за время переходного процесса, затем полные фазы
+    let (f, rk) = (MeasureResult::Freq(32768.0), MeasureResult::Rk(50.0));
+    let mut phases = vec![f, rk];
+    for mode in [f, rk, f, rk, f] {
+        phases.extend([mode; 5]);
+    }
+    script.lock().unwrap().extend(phases);
+
+    let events = collect_events(&mut events_rx).await;
+    assert_eq!(events.last(), Some(&ControllerEvent::MeasureFinished));
+    let switches = events
+        .iter()
+        .filter(|e| matches!(e, ControllerEvent::ModeSwitched { .. }))
+        .count();
+    assert_eq!(switches, SWITCH_CYCLES as usize * 2 + 1);
+
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+    assert_eq!(stat.rks.len(), 3);
+    assert_eq!(stat.freqs.len(), 4);
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_interrupt_reason_published() {
+    let mut ctrl = connected_controller(profile()).await;
+    let mut events_rx = ctrl.subscribe_events();
+
+    ctrl.start_mesure().unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    ctrl.interrupt_mesure().await;
+
+    let events = collect_events(&mut events_rx).await;
+    assert_eq!(events.first(), Some(&ControllerEvent::MeasureStarted));
+    assert_eq!(
+        events.last(),
+        Some(&ControllerEvent::MeasureInterrupted {
+            reason: InterruptReason::Canceled
+        })
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn device_errors_published() {
+    let mut ctrl = connected_controller(EmulationProfile {
+        error_probability: 1.0,
+        ..profile()
+    })
+    .await;
+    let mut events_rx = ctrl.subscribe_events();
+
+    ctrl.start_mesure().unwrap();
+    let events = collect_events(&mut events_rx).await;
+    assert!(
+        matches!(
+            events.last(),
+            Some(ControllerEvent::MeasureInterrupted {
+                reason: InterruptReason::DeviceError(_)
+            })
+        ),
+        "{:?}",
+        events
+    );
+
+    // связь теряется и восстанавливается повторным подключением
+    let mut device_error = false;
+    loop {
+        match tokio::time::timeout(Duration::from_secs(60), events_rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            ControllerEvent::DeviceError { .. } => device_error = true,
+            ControllerEvent::Connected => break,
+            _ => (),
+        }
+    }
+    assert!(
+        device_error
+            || events
+                .iter()
+                .any(|e| matches!(e, ControllerEvent::DeviceError { .. }))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_times_out_without_first_switch() {
+    // устройство не переключает режим
+    let mut ctrl = connected_controller(EmulationProfile {
+        switch_period_ms: 1_000_000,
+        ..profile()
+    })
+    .await;
+    let mut events_rx = ctrl.subscribe_events();
+
+    ctrl.set_timeouts(MeasureTimeouts::new(
+        Some(Duration::from_secs(2)),
+        None,
+        None,
+    ));
+    let start = tokio::time::Instant::now();
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+
+    assert_eq!(
+        stat.state,
+        MeasureProcessState::Interrupted(InterruptReason::Timeout(MeasureTimeout::FirstSwitch))
+    );
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed >= Duration::from_secs(2) && elapsed < Duration::from_secs(3),
+        "{:?}",
+        elapsed
+    );
+    assert_eq!(
+        collect_events(&mut events_rx).await.last(),
+        Some(&ControllerEvent::MeasureInterrupted {
+            reason: InterruptReason::Timeout(MeasureTimeout::FirstSwitch)
+        })
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_times_out_on_long_phase() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    // фаза длится switch_period_ms
+    ctrl.set_timeouts(MeasureTimeouts::new(
+        None,
+        Some(Duration::from_millis(profile().switch_period_ms / 2)),
+        None,
+    ));
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+
+    assert_eq!(
+        stat.state,
+        MeasureProcessState::Interrupted(InterruptReason::Timeout(MeasureTimeout::Phase))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_times_out_on_total_duration() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    ctrl.set_timeouts(MeasureTimeouts::new(
+        None,
+        None,
+        Some(Duration::from_millis(1500)),
+    ));
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+
+    assert_eq!(
+        stat.state,
+        MeasureProcessState::Interrupted(InterruptReason::Timeout(MeasureTimeout::Total))
+    );
+    assert!(!stat.samples.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn measure_within_timeouts_finishes() {
+    let mut ctrl = connected_controller(profile()).await;
+
+    let period = Duration::from_millis(profile().switch_period_ms);
+    ctrl.set_timeouts(MeasureTimeouts::new(
+        Some(period * 2),
+        Some(period * 2),
+        Some(period * (SWITCH_CYCLES * 2 + 2)),
+    ));
+    ctrl.start_mesure().unwrap();
+    let stat = wait_done(ctrl.subscribe_measure_status().unwrap()).await;
+
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+}
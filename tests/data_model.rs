@@ -0,0 +1,91 @@
+use clicker_data_collector::{
+    data_model::{DataModel, FrequencyOffset, ResonatorData},
+    FreqTolerance, MeasureProcessStat, ResonatorCatalog, ResonatorType,
+};
+
+fn resonator(freq: f32) -> ResonatorData {
+    MeasureProcessStat::from((&[freq][..], &[50.0][..]))
+        .try_into()
+        .unwrap()
+}
+
+fn catalog() -> ResonatorCatalog {
+    let mut catalog = ResonatorCatalog::default();
+    catalog.insert(ResonatorType {
+        name: "РК-32".to_string(),
+        nominal_freq: 32768.0,
+        freq_tolerance: FreqTolerance::Ppm(20.0),
+        max_rk: 70.0,
+        max_freq_iqr: 0.5,
+        max_rk_iqr: None,
+    });
+    catalog
+}
+
+#[test]
+fn offset_in_ppm() {
+    let offset = FrequencyOffset::new(32768.33, 32768.0);
+    assert!((offset.hz - 0.327_68).abs() < 1e-3, "{offset:?}");
+    assert!((offset.ppm - 10.0).abs() < 0.1, "{offset:?}");
+
+    let offset = FrequencyOffset::new(32000.0, 32000.0 * 1.000_05);
+    assert!((offset.ppm + 50.0).abs() < 0.1, "{offset:?}");
+}
+
+#[test]
+fn nominal_from_header_or_catalog() {
+    let catalog = catalog();
+    let mut data_model = DataModel::default();
+    assert_eq!(data_model.batch_nominal_freq(&catalog), None);
+
+    data_model.data_type = "РК-32".to_string();
+    assert_eq!(data_model.batch_nominal_freq(&catalog), Some(32768.0));
+
+    data_model.nominal_freq = Some(32000.0);
+    assert_eq!(data_model.batch_nominal_freq(&catalog), Some(32000.0));
+}
+
+#[test]
+fn batch_mean_offset() {
+    let catalog = catalog();
+    let mut data_model = DataModel::default();
+    data_model.resonators.push(resonator(32768.0));
+    data_model.resonators.push(resonator(32770.0));
+    data_model.resonators.push(resonator(32769.0));
+
+    // номинал неизвестен
+    data_model.evaluate(&catalog);
+    assert!(data_model
+        .resonators
+        .iter()
+        .all(|r| r.frequency_offset.is_none()));
+    assert_eq!(data_model.mean_offset(), None);
+
+    data_model.data_type = "РК-32".to_string();
+    data_model.evaluate(&catalog);
+    let offsets = data_model
+        .resonators
+        .iter()
+        .map(|r| r.frequency_offset.unwrap().hz)
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, vec![0.0, 2.0, 1.0]);
+
+    let mean = data_model.mean_offset().unwrap();
+    assert_eq!(mean.hz, 1.0);
+    assert!((mean.ppm - 1.0 / 32768.0 * 1e6).abs() < 1e-3, "{mean:?}");
+
+    // новый резонатор оценивается так же, как уже измеренные
+    let mut new_res = resonator(32767.5);
+    data_model.evaluate_resonator(&mut new_res, &catalog);
+    assert_eq!(new_res.frequency_offset.unwrap().hz, -0.5);
+    assert!(new_res.verdict.unwrap().passed);
+}
+
+#[test]
+fn single_mode_result_not_converted() {
+    let freqs_only = MeasureProcessStat::from((&[32768.0][..], &[][..]));
+    assert!(ResonatorData::try_from(freqs_only).is_err());
+
+    let rks_only = MeasureProcessStat::from((&[][..], &[50.0][..]));
+    assert!(ResonatorData::try_from(rks_only).is_err());
+}
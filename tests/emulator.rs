@@ -0,0 +1,106 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::net::TcpListener;
+
+use clicker_data_collector::{
+    Clicker, ClickerController, ClickerInterface, ConnectionState, DeviceEmulator,
+    EmulationProfile, MeasureProcessState, TCP_PREFIX,
+};
+
+const TIMEOUT: Duration = Duration::from_millis(500);
+const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+const SWITCH_CYCLES: u32 = 2;
+const SEED: u64 = 42;
+
+/// Эмулятор, обслуживающий подключения к локальному TCP порту, возвращает адрес устройства
+async fn spawn_emulator(profile: EmulationProfile) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = format!("{TCP_PREFIX}{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let mut emulator = DeviceEmulator::new(profile.clone());
+            tokio::spawn(async move { emulator.serve(stream).await });
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn emulator_injects_error_status() {
+    let port = spawn_emulator(EmulationProfile {
+        error_probability: 1.0,
+        ..Default::default()
+    })
+    .await;
+
+    // проверка связи не читает значения и проходит
+    let mut clicker = Clicker::connect(port, TIMEOUT, 0).await.unwrap();
+    assert_eq!(
+        format!("{:?}", clicker.read().await),
+        "Err(Protocol(ProtocolError))"
+    );
+}
+
+#[tokio::test]
+async fn measure_through_emulator() {
+    let port = spawn_emulator(EmulationProfile {
+        switch_period_ms: 100,
+        error_probability: 0.1,
+        seed: Some(SEED),
+        ..Default::default()
+    })
+    .await;
+
+    let mut ctrl = ClickerController::new(UPDATE_INTERVAL, SWITCH_CYCLES);
+
+    // полученные контроллером отсчеты
+    let readings = Arc::new(AtomicU64::new(0));
+    {
+        let (mut rx, readings) = (ctrl.subscribe(), readings.clone());
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                readings.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    }
+
+    ctrl.attach(move || Clicker::connect(port.clone(), TIMEOUT, 0));
+    ctrl.subscribe_connection_state()
+        .wait_for(|s| *s == ConnectionState::Connected)
+        .await
+        .unwrap();
+
+    ctrl.start_mesure().unwrap();
+    let stat = tokio::time::timeout(
+        Duration::from_secs(10),
+        ctrl.subscribe_measure_status().unwrap().wait_for(|s| {
+            !matches!(
+                s.state,
+                MeasureProcessState::Idle | MeasureProcessState::Running
+            )
+        }),
+    )
+    .await
+    .expect("measure did not finish in time")
+    .unwrap()
+    .clone();
+
+    assert_eq!(stat.state, MeasureProcessState::Finished);
+    assert!(!stat.freqs.is_empty());
+    assert!(!stat.rks.is_empty());
+    assert_eq!(ctrl.connection_state(), ConnectionState::Connected);
+
+    // часть ответов - ошибки, кроме них есть ответ на проверку связи
+    let diagnostics = ctrl.diagnostics();
+    assert_eq!(diagnostics.timeouts, 0);
+    assert!(
+        diagnostics.responses > readings.load(Ordering::SeqCst) + 1,
+        "{diagnostics:?}"
+    );
+}
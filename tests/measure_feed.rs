@@ -0,0 +1,87 @@
+use futures::StreamExt;
+
+use clicker_data_collector::{
+    AttemptStat, InterruptReason, MeasureFeed, MeasureProcessStat, MeasureProcessState,
+};
+
+fn attempt(attempt: u32, state: MeasureProcessState, retry: bool) -> AttemptStat {
+    AttemptStat {
+        attempt,
+        attempts: 2,
+        retry,
+        unstable: false,
+        stat: MeasureProcessStat {
+            state,
+            ..Default::default()
+        },
+    }
+}
+
+/// Номер попытки, состояние и признак повтора
+fn summary(stat: Option<AttemptStat>) -> Option<(u32, MeasureProcessState, bool)> {
+    stat.map(|s| (s.attempt, s.stat.state, s.retry))
+}
+
+#[tokio::test]
+async fn second_subscriber_follows_running_measure() {
+    let feed = MeasureFeed::new();
+    feed.publish(attempt(1, MeasureProcessState::Running, false));
+
+    let mut progress = Box::pin(feed.progress().expect("measure is running"));
+    assert_eq!(
+        summary(progress.next().await),
+        Some((1, MeasureProcessState::Running, false))
+    );
+
+    // повтор после завершенной попытки не заканчивает поток
+    let updates = [
+        attempt(1, MeasureProcessState::Running, false),
+        attempt(1, MeasureProcessState::Finished, true),
+        attempt(2, MeasureProcessState::Running, false),
+        attempt(2, MeasureProcessState::Finished, false),
+    ];
+    for update in updates {
+        let expected = (update.attempt, update.stat.state.clone(), update.retry);
+        feed.publish(update);
+        assert_eq!(summary(progress.next().await), Some(expected));
+    }
+
+    // последнее состояние завершает поток
+    assert!(progress.next().await.is_none());
+    assert!(!feed.is_running());
+}
+
+#[tokio::test]
+async fn interrupted_measure_ends_stream() {
+    let feed = MeasureFeed::new();
+    let mut stream = Box::pin(feed.stream(false));
+
+    feed.publish(attempt(1, MeasureProcessState::Running, false));
+    assert_eq!(
+        summary(stream.next().await),
+        Some((1, MeasureProcessState::Running, false))
+    );
+
+    let canceled = MeasureProcessState::Interrupted(InterruptReason::Canceled);
+    feed.publish(attempt(1, canceled.clone(), false));
+    assert_eq!(summary(stream.next().await), Some((1, canceled, false)));
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn no_progress_without_running_measure() {
+    let feed = MeasureFeed::new();
+    assert!(feed.current().is_none());
+    assert!(feed.progress().is_none());
+
+    feed.publish(attempt(1, MeasureProcessState::Finished, true));
+    assert!(feed.is_running());
+
+    feed.publish(attempt(2, MeasureProcessState::Finished, false));
+    assert!(feed.progress().is_none());
+    // состояние последнего измерения остается доступным
+    assert_eq!(
+        summary(feed.current()),
+        Some((2, MeasureProcessState::Finished, false))
+    );
+}
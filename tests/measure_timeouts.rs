@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use clicker_data_collector::{MeasureTimeout, MeasureTimeouts};
+use tokio::time::Instant;
+
+const SEC: Duration = Duration::from_secs(1);
+
+#[test]
+fn no_limits_no_deadline() {
+    let start = Instant::now();
+    let timeouts = MeasureTimeouts::default();
+
+    assert_eq!(timeouts.deadline(start, None), None);
+    assert_eq!(timeouts.deadline(start, Some(start + SEC)), None);
+}
+
+#[test]
+fn phase_limit_depends_on_switch() {
+    let start = Instant::now();
+    let timeouts = MeasureTimeouts::new(Some(SEC * 5), Some(SEC * 10), None);
+
+    assert_eq!(
+        timeouts.deadline(start, None),
+        Some((start + SEC * 5, MeasureTimeout::FirstSwitch))
+    );
+    assert_eq!(
+        timeouts.deadline(start, Some(start + SEC * 3)),
+        Some((start + SEC * 13, MeasureTimeout::Phase))
+    );
+}
+
+#[test]
+fn nearest_deadline_wins() {
+    let start = Instant::now();
+    let timeouts = MeasureTimeouts::new(Some(SEC * 5), Some(SEC * 10), Some(SEC * 20));
+
+    assert_eq!(
+        timeouts.deadline(start, None),
+        Some((start + SEC * 5, MeasureTimeout::FirstSwitch))
+    );
+    assert_eq!(
+        timeouts.deadline(start, Some(start + SEC * 15)),
+        Some((start + SEC * 20, MeasureTimeout::Total))
+    );
+
+    let total_only = MeasureTimeouts::new(None, None, Some(SEC * 20));
+    assert_eq!(
+        total_only.deadline(start, Some(start + SEC)),
+        Some((start + SEC * 20, MeasureTimeout::Total))
+    );
+}
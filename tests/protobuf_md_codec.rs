@@ -0,0 +1,143 @@
+use bytes::BytesMut;
+use proptest::prelude::*;
+use tokio_util::codec::{Decoder, Encoder};
+
+use clicker_data_collector::{ProtobufMDCodec, ProtobufMDDeviceCodec};
+
+type Response = <ProtobufMDCodec as Decoder>::Item;
+
+fn response(id: u32) -> Response {
+    Response {
+        id,
+        ..Default::default()
+    }
+}
+
+fn encode(resp: Response) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    ProtobufMDDeviceCodec::default()
+        .encode(resp, &mut buf)
+        .unwrap();
+    buf.to_vec()
+}
+
+/// Первый байт кадра
+fn magick() -> u8 {
+    encode(response(0))[0]
+}
+
+fn decode_all(codec: &mut ProtobufMDCodec, buf: &mut BytesMut) -> Vec<u32> {
+    let mut ids = vec![];
+    while let Some(resp) = codec.decode(buf).expect("decoder must not fail") {
+        ids.push(resp.id);
+    }
+    ids
+}
+
+fn noise_without_magick() -> impl Strategy<Value = Vec<u8>> {
+    let magick = magick();
+    prop::collection::vec(
+        any::<u8>().prop_filter("not magick", move |b| *b != magick),
+        0..32,
+    )
+}
+
+// Ложный Magick с правдоподобной длиной прямо перед кадром не задерживает кадр
+#[test]
+fn stray_magick_before_frame() {
+    let mut stream = vec![magick(), 0x40];
+    stream.extend(encode(response(42)));
+
+    let mut codec = ProtobufMDCodec::default();
+    let mut buf = BytesMut::from(stream.as_slice());
+    assert_eq!(decode_all(&mut codec, &mut buf), vec![42]);
+    assert!(buf.is_empty());
+
+    let stats = codec.stats();
+    assert_eq!(stats.dropped_bytes, 2);
+    assert_eq!(stats.resync_events, 1);
+}
+
+proptest! {
+    // Шум без Magick между кадрами полностью отбрасывается, все кадры декодируются
+    #[test]
+    fn frames_survive_noise(
+        chunks in prop::collection::vec((any::<u32>(), noise_without_magick()), 1..16)
+    ) {
+        let mut stream = vec![];
+        for (id, noise) in &chunks {
+            stream.extend_from_slice(noise);
+            stream.extend(encode(response(*id)));
+        }
+
+        let mut codec = ProtobufMDCodec::default();
+        let ids = decode_all(&mut codec, &mut BytesMut::from(stream.as_slice()));
+
+        prop_assert_eq!(ids, chunks.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+
+        let stats = codec.stats();
+        prop_assert_eq!(
+            stats.dropped_bytes,
+            chunks.iter().map(|(_, n)| n.len() as u64).sum::<u64>()
+        );
+        prop_assert_eq!(
+            stats.resync_events,
+            chunks.iter().filter(|(_, n)| !n.is_empty()).count() as u64
+        );
+        prop_assert_eq!(stats.decode_errors, 0);
+    }
+
+    // Кадр, поступающий по частям, декодируется ровно один раз
+    #[test]
+    fn partial_frames(id in any::<u32>(), split in prop::collection::vec(1usize..8, 1..16)) {
+        let frame = encode(response(id));
+        let mut codec = ProtobufMDCodec::default();
+        let mut buf = BytesMut::new();
+        let mut ids = vec![];
+
+        let mut pos = 0;
+        for step in split.iter().cycle() {
+            if pos >= frame.len() {
+                break;
+            }
+            let end = (pos + step).min(frame.len());
+            buf.extend_from_slice(&frame[pos..end]);
+            pos = end;
+            ids.extend(decode_all(&mut codec, &mut buf));
+        }
+
+        prop_assert_eq!(ids, vec![id]);
+        prop_assert!(buf.is_empty());
+    }
+
+    // Кадр за ложным Magick с любой допустимой длиной декодируется без ожидания продолжения
+    #[test]
+    fn stray_magick_with_length(len in 16u8..128, id in 1u32..) {
+        let mut stream = vec![magick(), len];
+        stream.extend(encode(response(id)));
+
+        let mut codec = ProtobufMDCodec::default();
+        let ids = decode_all(&mut codec, &mut BytesMut::from(stream.as_slice()));
+        prop_assert_eq!(ids, vec![id]);
+    }
+
+    // Произвольный шум (в т.ч. ложные Magick) не приводит к ошибке декодера,
+    // а кадр после достаточно длинного шума восстанавливается
+    #[test]
+    fn arbitrary_noise_never_fails(
+        noise in prop::collection::vec(any::<u8>(), 0..512),
+        id in any::<u32>(),
+    ) {
+        let mut codec = ProtobufMDCodec::default();
+        let mut buf = BytesMut::from(noise.as_slice());
+        decode_all(&mut codec, &mut buf);
+
+        // дополнить мусор до гарантированной потери ложного кадра
+        buf.extend_from_slice(&[0u8; 300]);
+        decode_all(&mut codec, &mut buf);
+
+        buf.extend(encode(response(id)));
+        let ids = decode_all(&mut codec, &mut buf);
+        prop_assert_eq!(ids.last().copied(), Some(id));
+    }
+}
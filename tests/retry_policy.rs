@@ -0,0 +1,78 @@
+use clicker_data_collector::{BestAttempt, RetryPolicy};
+
+#[test]
+fn attempts_include_first_measurement() {
+    assert_eq!(RetryPolicy::new(0, 0.1, 2.0).attempts(), 1);
+    assert!(!RetryPolicy::new(0, 0.1, 2.0).is_enabled());
+    assert_eq!(RetryPolicy::new(2, 0.1, 2.0).attempts(), 3);
+    assert_eq!(RetryPolicy::new(u32::MAX, 0.1, 2.0).attempts(), u32::MAX);
+}
+
+#[test]
+fn excess_is_worst_ratio_to_threshold() {
+    let policy = RetryPolicy::new(2, 0.1, 2.0);
+
+    assert!((policy.excess(0.05, 1.0) - 0.5).abs() < 1e-6);
+    assert!((policy.excess(0.05, 3.0) - 1.5).abs() < 1e-6);
+    assert!((policy.excess(0.3, 1.0) - 3.0).abs() < 1e-6);
+
+    assert!(policy.is_stable(0.1, 2.0));
+    assert!(!policy.is_stable(0.11, 2.0));
+    assert!(!policy.is_stable(0.1, 2.1));
+}
+
+#[test]
+fn unknown_deviation_is_unstable() {
+    let policy = RetryPolicy::new(1, 0.1, 2.0);
+
+    assert_eq!(policy.excess(f32::NAN, 1.0), f32::INFINITY);
+    assert_eq!(policy.excess(0.05, f32::NAN), f32::INFINITY);
+    assert!(!policy.is_stable(f32::NAN, f32::NAN));
+}
+
+#[test]
+fn best_attempt_keeps_lowest_spread() {
+    let mut best = BestAttempt::new(RetryPolicy::new(3, 0.1, 2.0));
+
+    assert!((best.push(1, 0.3, 1.0) - 3.0).abs() < 1e-6);
+    assert!(best.push(2, 0.15, 1.0) < 3.0);
+    best.push(3, 0.2, 1.0);
+    best.push(4, f32::NAN, 1.0);
+
+    assert_eq!(best.take(true), Some((2, true)));
+    assert_eq!(best.take(true), None);
+}
+
+#[test]
+fn best_attempt_retries_until_attempts_exhausted() {
+    let best = BestAttempt::<()>::new(RetryPolicy::new(2, 0.1, 2.0));
+
+    assert!(best.need_retry(1, 1.5));
+    assert!(best.need_retry(2, f32::INFINITY));
+    assert!(!best.need_retry(3, 1.5));
+    assert!(!best.need_retry(1, 1.0));
+}
+
+#[test]
+fn best_attempt_flags_unstable() {
+    // стабильная попытка из нескольких
+    let mut best = BestAttempt::new(RetryPolicy::new(2, 0.1, 2.0));
+    best.push("noisy", 0.3, 1.0);
+    best.push("stable", 0.05, 1.0);
+    assert_eq!(best.take(true), Some(("stable", false)));
+
+    // все попытки с большим разбросом
+    best.push("noisy", 0.3, 1.0);
+    best.push("noisier", 0.5, 1.0);
+    assert_eq!(best.take(true), Some(("noisy", true)));
+
+    // прерванное измерение не нестабильное
+    best.push("noisy", 0.3, 1.0);
+    assert_eq!(best.take(false), Some(("noisy", false)));
+
+    // без повторов разброс не проверяется
+    let mut best = BestAttempt::new(RetryPolicy::new(0, 0.1, 2.0));
+    best.push("noisy", 0.3, 1.0);
+    assert!(!best.need_retry(1, 3.0));
+    assert_eq!(best.take(true), Some(("noisy", false)));
+}
@@ -0,0 +1,76 @@
+use clicker_data_collector::{
+    Repeatability, Segment, SegmentMode, StatisticsOptions, MIN_SEGMENT_SAMPLES,
+};
+
+fn open_segment(mode: SegmentMode, values: &[f32]) -> Segment {
+    let mut segment = Segment::new(mode);
+    let options = StatisticsOptions::default();
+    values.iter().for_each(|v| segment.push(*v, &options));
+    segment
+}
+
+/// Фаза, записанная целиком
+fn segment(mode: SegmentMode, values: &[f32]) -> Segment {
+    let mut segment = open_segment(mode, values);
+    segment.close();
+    segment
+}
+
+#[test]
+fn repeatability_of_segment_medians() {
+    let segments = [
+        segment(SegmentMode::Freq, &[0.0, 1.0, 2.0]),
+        segment(SegmentMode::Rk, &[100.0, 200.0, 300.0]),
+        segment(SegmentMode::Freq, &[2.0, 2.0, 2.0]),
+        segment(SegmentMode::Rk, &[100.0, 100.0]),
+        segment(SegmentMode::Freq, &[3.0, 3.0, 4.0]),
+    ];
+
+    let freq = Repeatability::new(&segments, SegmentMode::Freq).unwrap();
+    assert_eq!(freq.cycles, 3);
+    assert_eq!(freq.range, 2.0);
+    assert!((freq.std_dev - 1.0).abs() < 1e-6, "{freq:?}");
+
+    let rk = Repeatability::new(&segments, SegmentMode::Rk).unwrap();
+    assert_eq!(rk.cycles, 2);
+    assert_eq!(rk.range, 100.0);
+    assert!((rk.std_dev - 70.710_68).abs() < 1e-3, "{rk:?}");
+}
+
+#[test]
+fn repeatability_needs_two_segments() {
+    let segments = [
+        segment(SegmentMode::Freq, &[1.0, 2.0]),
+        segment(SegmentMode::Rk, &[f32::NAN]),
+        segment(SegmentMode::Rk, &[10.0]),
+    ];
+
+    assert!(Repeatability::new(&segments, SegmentMode::Freq).is_none());
+    assert!(Repeatability::new(&segments, SegmentMode::Rk).is_none());
+    assert!(Repeatability::new(&[], SegmentMode::Freq).is_none());
+}
+
+#[test]
+fn repeatability_skips_incomplete_and_short_segments() {
+    let segments = [
+        open_segment(SegmentMode::Freq, &[50.0, 50.0, 50.0]),
+        segment(SegmentMode::Rk, &[100.0, 100.0]),
+        segment(SegmentMode::Freq, &[1.0, 1.0]),
+        segment(SegmentMode::Rk, &[200.0; MIN_SEGMENT_SAMPLES - 1]),
+        segment(SegmentMode::Freq, &[2.0, 2.0, 2.0]),
+        segment(SegmentMode::Rk, &[110.0, 110.0]),
+        // отсчет, завершивший измерение
+        open_segment(SegmentMode::Freq, &[70.0]),
+    ];
+
+    assert!(!segments[0].complete);
+    assert!(segments[1].complete);
+
+    let freq = Repeatability::new(&segments, SegmentMode::Freq).unwrap();
+    assert_eq!(freq.cycles, 2);
+    assert_eq!(freq.range, 1.0);
+
+    let rk = Repeatability::new(&segments, SegmentMode::Rk).unwrap();
+    assert_eq!(rk.cycles, 2);
+    assert_eq!(rk.range, 10.0);
+}
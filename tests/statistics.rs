@@ -0,0 +1,233 @@
+use clicker_data_collector::{
+    statistics::student_t_quantile, OutlierMethod, OutlierRejection, QuantileMethod, Statistics,
+    StatisticsOptions,
+};
+
+const ONE_TO_TEN: [f64; 10] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+
+fn assert_close(actual: f64, expected: f64, eps: f64) {
+    assert!(
+        (actual - expected).abs() < eps,
+        "actual: {actual}, expected: {expected}"
+    );
+}
+
+fn with_method(series: &[f64], quantile_method: QuantileMethod) -> Statistics<f64> {
+    Statistics::with_options(
+        series,
+        &StatisticsOptions {
+            quantile_method,
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn quartiles_match_reference() {
+    // numpy.percentile(range(1, 11), [25, 75], method=...)
+    let reference = [
+        (QuantileMethod::Lower, 3.0, 7.0),
+        (QuantileMethod::Higher, 4.0, 8.0),
+        (QuantileMethod::Nearest, 3.0, 8.0),
+        (QuantileMethod::Midpoint, 3.5, 7.5),
+        (QuantileMethod::Linear, 3.25, 7.75),
+        (QuantileMethod::Weibull, 2.75, 8.25),
+        (QuantileMethod::Hazen, 3.0, 8.0),
+        (QuantileMethod::MedianUnbiased, 2.916_667, 8.083_333),
+    ];
+
+    for (method, q1, q3) in reference {
+        let stat = with_method(&ONE_TO_TEN, method);
+        assert_close(stat.q1(), q1, 1e-6);
+        assert_close(stat.q3(), q3, 1e-6);
+        assert_close(stat.iqr(), q3 - q1, 1e-6);
+    }
+}
+
+#[test]
+fn median_of_even_and_odd_series() {
+    assert_eq!(Statistics::new(&ONE_TO_TEN).median(), 5.5);
+    assert_eq!(Statistics::new(&ONE_TO_TEN[..9]).median(), 5.0);
+    assert_eq!(
+        with_method(&ONE_TO_TEN, QuantileMethod::Lower).median(),
+        5.0
+    );
+    assert_eq!(
+        with_method(&ONE_TO_TEN, QuantileMethod::Higher).median(),
+        6.0
+    );
+}
+
+#[test]
+fn moments_match_reference() {
+    let series = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+    let stat = Statistics::new(&series);
+
+    assert_eq!(stat.count(), 8);
+    assert_eq!(stat.min(), 2.0);
+    assert_eq!(stat.max(), 9.0);
+    assert_eq!(stat.mean(), 5.0);
+    assert_close(stat.std_dev(), (32.0f64 / 7.0).sqrt(), 1e-12);
+    assert_close(
+        stat.std_err(),
+        (32.0f64 / 7.0).sqrt() / 8.0f64.sqrt(),
+        1e-12,
+    );
+
+    // mean ± t(0.975, 7) * s / sqrt(n)
+    let (lower, upper) = stat.confidence_interval();
+    assert_close(lower, 3.212_52, 1e-4);
+    assert_close(upper, 6.787_48, 1e-4);
+}
+
+#[test]
+fn student_t_matches_table() {
+    let reference = [
+        (0.95, 1, 12.706),
+        (0.95, 2, 4.303),
+        (0.95, 3, 3.182),
+        (0.95, 5, 2.571),
+        (0.95, 10, 2.228),
+        (0.95, 30, 2.042),
+        (0.99, 4, 4.604),
+        (0.99, 15, 2.947),
+        (0.90, 7, 1.895),
+        (0.95, 1000, 1.962),
+    ];
+
+    for (confidence, df, t) in reference {
+        assert_close(student_t_quantile(confidence, df), t, 1e-3);
+    }
+    assert!(student_t_quantile(0.95, 0).is_nan());
+    assert!(student_t_quantile(1.0, 5).is_nan());
+}
+
+#[test]
+fn fence_multiplier() {
+    let series = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 100.0];
+
+    let stat = Statistics::new(&series);
+    assert_eq!(stat.fence(), 1.5);
+    assert_eq!(stat.lower_bound(), stat.q1() - 1.5 * stat.iqr());
+    assert_eq!(stat.upper_bound(), stat.q3() + 1.5 * stat.iqr());
+    assert!(stat.is_outlier(100.0));
+    assert!(!stat.is_outlier(10.0));
+
+    let wide = Statistics::with_options(
+        &series,
+        &StatisticsOptions {
+            fence: 20.0,
+            ..Default::default()
+        },
+    );
+    assert_eq!(wide.upper_bound(), wide.q3() + 20.0 * wide.iqr());
+    assert!(!wide.is_outlier(100.0));
+}
+
+#[test]
+fn nan_ignored() {
+    let stat = Statistics::new(&[f32::NAN, 1.0, 2.0, f32::NAN, 3.0]);
+    assert_eq!(stat.count(), 3);
+    assert_eq!(stat.median(), 2.0);
+    assert_eq!(stat.mean(), 2.0);
+}
+
+#[test]
+fn empty_and_single_series() {
+    let empty = Statistics::<f32>::new(&[]);
+    assert_eq!(empty.count(), 0);
+    assert!(empty.median().is_nan());
+    assert!(empty.mean().is_nan());
+    assert!(empty.min().is_nan());
+
+    let single = Statistics::new(&[42.0f32]);
+    assert_eq!(single.count(), 1);
+    assert_eq!(single.median(), 42.0);
+    assert_eq!(single.mean(), 42.0);
+    assert_eq!(single.iqr(), 0.0);
+    assert!(single.std_dev().is_nan());
+    assert!(single.confidence_interval().0.is_nan());
+}
+
+#[test]
+fn serialized_for_ui() {
+    let json = serde_json::to_value(Statistics::new(&[1.0f32, 2.0, 3.0])).unwrap();
+    for key in [
+        "count",
+        "min",
+        "max",
+        "mean",
+        "std_dev",
+        "std_err",
+        "median",
+        "q1",
+        "q3",
+        "iqr",
+        "fence",
+        "lower_bound",
+        "upper_bound",
+        "confidence",
+        "ci_lower",
+        "ci_upper",
+    ] {
+        assert!(json.get(key).is_some(), "no {key} in {json}");
+    }
+    assert_eq!(json["count"], 3);
+    assert_eq!(json["median"], 2.0);
+}
+
+#[test]
+fn tukey_rejection() {
+    let series = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 100.0];
+    let rejection = OutlierRejection {
+        method: OutlierMethod::Tukey,
+        multiplier: 1.5,
+    };
+
+    // q1 = 3.5, q3 = 8.5
+    let (lower, upper) = rejection.bounds(&series, QuantileMethod::Linear).unwrap();
+    assert_close(lower, -4.0, 1e-9);
+    assert_close(upper, 16.0, 1e-9);
+
+    let (cleaned, rejected) = rejection.reject(&series, QuantileMethod::Linear);
+    assert_eq!(rejected, vec![10]);
+    assert_eq!(cleaned, ONE_TO_TEN.to_vec());
+}
+
+#[test]
+fn mad_rejection() {
+    let series = [3.0f32, 100.0, 1.0, 2.0, 4.0];
+    let rejection = OutlierRejection {
+        method: OutlierMethod::Mad,
+        multiplier: 3.0,
+    };
+
+    // медиана 3, MAD = 1
+    let (lower, upper) = rejection.bounds(&series, QuantileMethod::Linear).unwrap();
+    assert_close(lower as f64, 3.0 - 3.0 * OutlierRejection::MAD_SCALE, 1e-5);
+    assert_close(upper as f64, 3.0 + 3.0 * OutlierRejection::MAD_SCALE, 1e-5);
+
+    let (cleaned, rejected) = rejection.reject(&series, QuantileMethod::Linear);
+    assert_eq!(rejected, vec![1]);
+    assert_eq!(cleaned, vec![3.0, 1.0, 2.0, 4.0]);
+}
+
+#[test]
+fn no_rejection_by_default() {
+    let series = [1.0f32, 2.0, f32::NAN, 1000.0];
+    let rejection = OutlierRejection::default();
+    assert_eq!(rejection.method, OutlierMethod::None);
+    assert!(rejection.bounds(&series, QuantileMethod::Linear).is_none());
+
+    let (cleaned, rejected) = rejection.reject(&series, QuantileMethod::Linear);
+    assert!(rejected.is_empty());
+    assert_eq!(cleaned.len(), series.len());
+
+    // NaN не выброс, его пропускает статистика
+    let tukey = OutlierRejection {
+        method: OutlierMethod::Tukey,
+        multiplier: 1.5,
+    };
+    let (cleaned, _) = tukey.reject(&series, QuantileMethod::Linear);
+    assert!(cleaned.iter().any(|v| v.is_nan()));
+}